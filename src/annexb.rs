@@ -0,0 +1,197 @@
+//! Reading of AV1 Annex B length delimited bitstreams.
+//!
+//! Annex B streams consist of temporal units prefixed by their size, which in turn consist of
+//! size prefixed frame units containing size prefixed OBUs. `dav1d` expects the low overhead
+//! bitstream format of section 5 instead, so all temporal units are converted to that format.
+
+use crate::obu::{leb128, read_leb128, write_leb128, ObuHeader, OBU_HAS_SIZE_FIELD};
+use std::io::{self, Read};
+
+/// A temporal unit of an Annex B stream converted to the low overhead bitstream format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemporalUnit {
+    /// Temporal unit data in low overhead bitstream format.
+    pub data: Box<[u8]>,
+    /// Byte offset of the temporal unit in the Annex B stream.
+    pub offset: u64,
+}
+
+/// Annex B stream reader.
+#[derive(Debug)]
+pub struct Reader<R: Read> {
+    reader: R,
+    pos: u64,
+}
+
+impl<R: Read> Reader<R> {
+    /// Creates a new [`Reader`] reading from `reader`.
+    pub fn new(reader: R) -> Self {
+        Reader { reader, pos: 0 }
+    }
+
+    /// Reads the next temporal unit and converts it to the low overhead bitstream format.
+    ///
+    /// Returns `Ok(None)` at the end of the stream.
+    pub fn read_temporal_unit(&mut self) -> io::Result<Option<TemporalUnit>> {
+        let offset = self.pos;
+
+        let (size, size_len) = match read_leb128(&mut self.reader)? {
+            Some(v) => v,
+            None => return Ok(None),
+        };
+
+        let mut data = Vec::new();
+        let read = (&mut self.reader).take(size).read_to_end(&mut data)?;
+        if (read as u64) < size {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Truncated temporal unit",
+            ));
+        }
+        self.pos += (size_len + read) as u64;
+
+        Ok(Some(TemporalUnit {
+            data: temporal_unit_to_section5(&data)?.into_boxed_slice(),
+            offset,
+        }))
+    }
+
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: Read> Iterator for Reader<R> {
+    type Item = io::Result<TemporalUnit>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_temporal_unit().transpose()
+    }
+}
+
+/// Converts the content of an Annex B temporal unit, i.e. the frame units following the
+/// `temporal_unit_size`, to the low overhead bitstream format.
+pub fn temporal_unit_to_section5(mut buf: &[u8]) -> io::Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(buf.len());
+
+    while !buf.is_empty() {
+        let frame_unit = take_sized(&mut buf, "frame unit")?;
+        frame_unit_to_section5(frame_unit, &mut out)?;
+    }
+
+    Ok(out)
+}
+
+fn frame_unit_to_section5(mut buf: &[u8], out: &mut Vec<u8>) -> io::Result<()> {
+    while !buf.is_empty() {
+        let obu = take_sized(&mut buf, "OBU")?;
+        let (header, header_len) = ObuHeader::parse(obu)?;
+
+        if header.has_size_field {
+            out.extend_from_slice(obu);
+        } else {
+            out.push(obu[0] | OBU_HAS_SIZE_FIELD);
+            out.extend_from_slice(&obu[1..header_len]);
+            write_leb128(out, (obu.len() - header_len) as u64);
+            out.extend_from_slice(&obu[header_len..]);
+        }
+    }
+
+    Ok(())
+}
+
+/// Splits a leb128 size prefixed unit off the front of `buf`.
+fn take_sized<'a>(buf: &mut &'a [u8], what: &str) -> io::Result<&'a [u8]> {
+    let (size, size_len) = leb128(buf)?;
+    *buf = &buf[size_len..];
+
+    if size > buf.len() as u64 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Truncated {}", what),
+        ));
+    }
+
+    let (unit, rest) = buf.split_at(size as usize);
+    *buf = rest;
+
+    Ok(unit)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{ivf, obu};
+
+    static TEST_FILE_420_8: &[u8] = include_bytes!("../test-420-8.ivf");
+
+    // Converts a temporal unit in low overhead bitstream format to Annex B, putting all OBUs
+    // into a single frame unit and stripping their size fields.
+    fn section5_to_annexb(tu: &[u8], out: &mut Vec<u8>) {
+        let mut frame_unit = Vec::new();
+        for obu in obu::iter(tu) {
+            let obu = obu.unwrap();
+            let header_len = if obu.header.extension.is_some() { 2 } else { 1 };
+
+            let data = obu.data();
+            let mut stripped = vec![data[0] & !OBU_HAS_SIZE_FIELD];
+            stripped.extend_from_slice(&data[1..header_len]);
+            stripped.extend_from_slice(obu.payload());
+            write_leb128(&mut frame_unit, stripped.len() as u64);
+            frame_unit.extend_from_slice(&stripped);
+        }
+
+        let mut temporal_unit = Vec::new();
+        write_leb128(&mut temporal_unit, frame_unit.len() as u64);
+        temporal_unit.extend_from_slice(&frame_unit);
+
+        write_leb128(out, temporal_unit.len() as u64);
+        out.extend_from_slice(&temporal_unit);
+    }
+
+    #[test]
+    fn test_read() {
+        let packets = ivf::Reader::new(TEST_FILE_420_8)
+            .unwrap()
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+
+        let mut annexb = Vec::new();
+        let mut offsets = Vec::new();
+        for packet in &packets {
+            offsets.push(annexb.len() as u64);
+            section5_to_annexb(&packet.data, &mut annexb);
+        }
+
+        let tus = Reader::new(&annexb[..])
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(tus.len(), packets.len());
+        for ((tu, packet), offset) in tus.iter().zip(&packets).zip(offsets) {
+            assert_eq!(tu.data, packet.data);
+            assert_eq!(tu.offset, offset);
+        }
+    }
+
+    #[test]
+    fn test_invalid() {
+        // Temporal unit larger than the remaining data
+        let err = Reader::new(&[0x05, 0x01, 0x00][..])
+            .read_temporal_unit()
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        // Frame unit larger than the temporal unit
+        let err = temporal_unit_to_section5(&[0x03, 0x02, 0x10]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // Unterminated leb128
+        let err = temporal_unit_to_section5(&[0x80; 9]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // Empty OBU
+        let err = temporal_unit_to_section5(&[0x01, 0x00]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
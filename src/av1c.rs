@@ -0,0 +1,132 @@
+//! Parsing of the AV1 codec configuration record.
+//!
+//! The `av1C` record is stored by containers like ISOBMFF and Matroska and describes the stream
+//! parameters together with the sequence header of the stream.
+
+use crate::{parse_sequence_header, Error, SequenceHeader};
+use std::io;
+
+const HEADER_LEN: usize = 4;
+const MARKER_VERSION: u8 = 0x81;
+
+/// AV1 codec configuration record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Av1CodecConfiguration {
+    /// AV1 profile of the stream.
+    pub seq_profile: u8,
+    /// Level of the first operating point.
+    pub seq_level_idx_0: u8,
+    /// Tier of the first operating point.
+    pub seq_tier_0: u8,
+    /// Whether the stream has a bit depth higher than 8.
+    pub high_bitdepth: bool,
+    /// Whether the stream has a bit depth of 12.
+    pub twelve_bit: bool,
+    /// Whether the stream only has a luma plane.
+    pub monochrome: bool,
+    /// Whether the chroma planes are horizontally subsampled.
+    pub chroma_subsampling_x: bool,
+    /// Whether the chroma planes are vertically subsampled.
+    pub chroma_subsampling_y: bool,
+    /// Raw `chroma_sample_position` of the sequence header.
+    pub chroma_sample_position: u8,
+    /// Number of samples to buffer before presenting the first one, if signalled.
+    pub initial_presentation_delay: Option<u8>,
+    /// Sequence header and metadata OBUs of the stream.
+    pub config_obus: Vec<u8>,
+}
+
+impl Av1CodecConfiguration {
+    /// Parses an `av1C` record, e.g. the payload of an ISOBMFF `av1C` box or the Matroska
+    /// `CodecPrivate` of an AV1 track.
+    pub fn parse(buf: &[u8]) -> io::Result<Self> {
+        if buf.len() < HEADER_LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Truncated av1C record",
+            ));
+        }
+
+        if buf[0] != MARKER_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unsupported av1C marker and version {:#x}", buf[0]),
+            ));
+        }
+
+        Ok(Av1CodecConfiguration {
+            seq_profile: buf[1] >> 5,
+            seq_level_idx_0: buf[1] & 0x1f,
+            seq_tier_0: buf[2] >> 7,
+            high_bitdepth: buf[2] & 0x40 != 0,
+            twelve_bit: buf[2] & 0x20 != 0,
+            monochrome: buf[2] & 0x10 != 0,
+            chroma_subsampling_x: buf[2] & 0x08 != 0,
+            chroma_subsampling_y: buf[2] & 0x04 != 0,
+            chroma_sample_position: buf[2] & 0x03,
+            initial_presentation_delay: if buf[3] & 0x10 != 0 {
+                Some((buf[3] & 0x0f) + 1)
+            } else {
+                None
+            },
+            config_obus: buf[HEADER_LEN..].to_vec(),
+        })
+    }
+
+    /// Bit depth of the stream.
+    pub fn bit_depth(&self) -> u8 {
+        match (self.high_bitdepth, self.twelve_bit) {
+            (false, _) => 8,
+            (true, false) => 10,
+            (true, true) => 12,
+        }
+    }
+
+    /// Parses the sequence header from the configuration OBUs.
+    ///
+    /// Returns `None` if the record contains no configuration OBUs.
+    pub fn sequence_header(&self) -> Option<Result<SequenceHeader, Error>> {
+        if self.config_obus.is_empty() {
+            return None;
+        }
+
+        Some(parse_sequence_header(&self.config_obus))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let config = Av1CodecConfiguration::parse(&[0x81, 0x48, 0x6e, 0x1a, 0x0a, 0x00]).unwrap();
+        assert_eq!(
+            config,
+            Av1CodecConfiguration {
+                seq_profile: 2,
+                seq_level_idx_0: 8,
+                seq_tier_0: 0,
+                high_bitdepth: true,
+                twelve_bit: true,
+                monochrome: false,
+                chroma_subsampling_x: true,
+                chroma_subsampling_y: true,
+                chroma_sample_position: 2,
+                initial_presentation_delay: Some(11),
+                config_obus: vec![0x0a, 0x00],
+            }
+        );
+        assert_eq!(config.bit_depth(), 12);
+
+        let config = Av1CodecConfiguration::parse(&[0x81, 0x00, 0x0c, 0x00]).unwrap();
+        assert_eq!(config.bit_depth(), 8);
+        assert_eq!(config.initial_presentation_delay, None);
+        assert!(config.sequence_header().is_none());
+
+        let err = Av1CodecConfiguration::parse(&[0x81, 0x00]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let err = Av1CodecConfiguration::parse(&[0x01, 0x00, 0x00, 0x00]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
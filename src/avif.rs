@@ -0,0 +1,757 @@
+//! Decoding of AVIF still images.
+//!
+//! Only single AV1 coded images are supported, grid images and image sequences are not. The
+//! transformations of the image properties are reported but not applied to the decoded pictures.
+
+use crate::av1c::Av1CodecConfiguration;
+use crate::bmff::{find_box, for_each_box, invalid_data, ByteReader};
+use crate::{Decoder, Error, Picture, Settings};
+use std::fmt;
+use std::io;
+
+const ALPHA_AUXILIARY_TYPES: [&str; 2] = [
+    "urn:mpeg:mpegB:cicp:systems:auxiliary:alpha",
+    "urn:mpeg:hevc:2015:auxid:1",
+];
+
+/// CICP color description of an `nclx` color property.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Nclx {
+    /// Raw `colour_primaries` as defined in ITU-T H.273.
+    pub color_primaries: u16,
+    /// Raw `transfer_characteristics` as defined in ITU-T H.273.
+    pub transfer_characteristics: u16,
+    /// Raw `matrix_coefficients` as defined in ITU-T H.273.
+    pub matrix_coefficients: u16,
+    /// Whether full range sample values are used.
+    pub full_range: bool,
+}
+
+/// Clean aperture of an image, i.e. the cropping to apply.
+///
+/// All values are fractions of numerator and denominator. The offsets are relative to the center
+/// of the image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CleanAperture {
+    pub width_n: u32,
+    pub width_d: u32,
+    pub height_n: u32,
+    pub height_d: u32,
+    pub horiz_off_n: i32,
+    pub horiz_off_d: u32,
+    pub vert_off_n: i32,
+    pub vert_off_d: u32,
+}
+
+/// Axis of an image mirroring.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MirrorAxis {
+    /// Mirroring about a vertical axis, i.e. flipping left and right.
+    Vertical,
+    /// Mirroring about a horizontal axis, i.e. flipping top and bottom.
+    Horizontal,
+}
+
+/// Properties of an image item.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ItemProperties {
+    /// Width and height of the image from the `ispe` property.
+    pub size: Option<(u32, u32)>,
+    /// Bits per channel of the image from the `pixi` property.
+    pub bits_per_channel: Option<Vec<u8>>,
+    /// Codec configuration from the `av1C` property.
+    pub codec_configuration: Option<Av1CodecConfiguration>,
+    /// CICP color description from a `colr` property.
+    pub nclx: Option<Nclx>,
+    /// ICC profile from a `colr` property.
+    pub icc_profile: Option<Vec<u8>>,
+    /// Clean aperture from the `clap` property, applied first.
+    pub clean_aperture: Option<CleanAperture>,
+    /// Counter-clockwise rotation in degrees from the `irot` property, applied second.
+    pub rotation: Option<u16>,
+    /// Mirroring from the `imir` property, applied last.
+    pub mirror: Option<MirrorAxis>,
+    /// Type of an auxiliary image from the `auxC` property.
+    pub auxiliary_type: Option<String>,
+}
+
+/// An image item of an AVIF file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Item {
+    /// Item ID.
+    pub id: u32,
+    /// Item type, `av01` for AV1 coded images.
+    pub item_type: [u8; 4],
+    /// Item properties.
+    pub properties: ItemProperties,
+    extents: Vec<Extent>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Extent {
+    in_idat: bool,
+    offset: u64,
+    // Zero for extents reaching to the end of the data
+    length: u64,
+}
+
+/// Parsed structure of an AVIF file.
+#[derive(Debug, Clone)]
+pub struct Container<'a> {
+    /// Primary image item.
+    pub primary: Item,
+    /// Alpha auxiliary image item of the primary item, if any.
+    pub alpha: Option<Item>,
+    /// Whether the color values of the primary item are premultiplied by the alpha values.
+    pub premultiplied_alpha: bool,
+    buf: &'a [u8],
+    idat: Option<&'a [u8]>,
+}
+
+/// A decoded AVIF image.
+#[derive(Debug, Clone)]
+pub struct Image {
+    /// Color picture.
+    pub color: Picture,
+    /// Alpha picture, the alpha values are stored in the luma plane.
+    pub alpha: Option<Picture>,
+    /// Whether the color values are premultiplied by the alpha values.
+    pub premultiplied_alpha: bool,
+    /// Properties of the primary item, including the transformations to apply for display.
+    pub properties: ItemProperties,
+}
+
+/// Error returned when decoding AVIF files.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum AvifError {
+    /// The file is malformed or uses unsupported features.
+    Parse(io::Error),
+    /// The image could not be decoded.
+    Decode(Error),
+}
+
+impl fmt::Display for AvifError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AvifError::Parse(err) => write!(fmt, "Invalid AVIF file: {}", err),
+            AvifError::Decode(err) => write!(fmt, "Decoding failed: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for AvifError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AvifError::Parse(err) => Some(err),
+            AvifError::Decode(err) => Some(err),
+        }
+    }
+}
+
+impl From<io::Error> for AvifError {
+    fn from(err: io::Error) -> Self {
+        AvifError::Parse(err)
+    }
+}
+
+impl From<Error> for AvifError {
+    fn from(err: Error) -> Self {
+        AvifError::Decode(err)
+    }
+}
+
+/// Decodes the primary image of an AVIF file together with its alpha channel.
+pub fn decode(buf: &[u8]) -> Result<Image, AvifError> {
+    Container::parse(buf)?.decode()
+}
+
+impl<'a> Container<'a> {
+    /// Parses the structure of an AVIF file.
+    pub fn parse(buf: &'a [u8]) -> io::Result<Self> {
+        let ftyp = find_box(buf, b"ftyp")?.ok_or_else(|| invalid_data("No ftyp box"))?;
+        let mut r = ByteReader::new(ftyp);
+        let major_brand = r.bytes(4)?;
+        r.skip(4)?;
+        let is_avif = major_brand == b"avif" || r.rest().chunks_exact(4).any(|b| b == b"avif");
+        if !is_avif {
+            return Err(invalid_data("Not an AVIF file"));
+        }
+
+        let meta = find_box(buf, b"meta")?.ok_or_else(|| invalid_data("No meta box"))?;
+        let mut r = ByteReader::new(meta);
+        r.full_box_header()?;
+        let meta = r.rest();
+
+        let pitm = find_box(meta, b"pitm")?.ok_or_else(|| invalid_data("No primary item"))?;
+        let mut r = ByteReader::new(pitm);
+        let (version, _) = r.full_box_header()?;
+        let primary_id = read_item_id(&mut r, version != 0)?;
+
+        let item_types = parse_iinf(meta)?;
+        let mut extents = parse_iloc(meta)?;
+        let mut properties = parse_iprp(meta)?;
+        let references = match find_box(meta, b"iref")? {
+            Some(iref) => parse_iref(iref)?,
+            None => Vec::new(),
+        };
+
+        let alpha_id = references
+            .iter()
+            .filter(|r| &r.reference_type == b"auxl" && r.to_ids.contains(&primary_id))
+            .map(|r| r.from_id)
+            .find(|&id| {
+                properties.iter().any(|(item_id, p)| {
+                    *item_id == id
+                        && p.auxiliary_type
+                            .as_deref()
+                            .is_some_and(|t| ALPHA_AUXILIARY_TYPES.contains(&t))
+                })
+            });
+
+        let mut take_item = |id: u32| -> io::Result<Item> {
+            let item_type = item_types
+                .iter()
+                .find(|(item_id, _)| *item_id == id)
+                .map(|(_, item_type)| *item_type)
+                .ok_or_else(|| invalid_data(format!("No item info for item {}", id)))?;
+            if &item_type != b"av01" {
+                return Err(invalid_data(format!(
+                    "Unsupported item type {}",
+                    String::from_utf8_lossy(&item_type)
+                )));
+            }
+
+            Ok(Item {
+                id,
+                item_type,
+                properties: take_by_id(&mut properties, id).unwrap_or_default(),
+                extents: take_by_id(&mut extents, id)
+                    .ok_or_else(|| invalid_data(format!("No location for item {}", id)))?,
+            })
+        };
+
+        let primary = take_item(primary_id)?;
+        let alpha = alpha_id.map(&mut take_item).transpose()?;
+
+        let premultiplied_alpha = alpha.as_ref().is_some_and(|alpha| {
+            references.iter().any(|r| {
+                &r.reference_type == b"prem"
+                    && r.from_id == primary_id
+                    && r.to_ids.contains(&alpha.id)
+            })
+        });
+
+        Ok(Container {
+            primary,
+            alpha,
+            premultiplied_alpha,
+            buf,
+            idat: find_box(meta, b"idat")?,
+        })
+    }
+
+    /// Returns the coded data of an item.
+    pub fn item_data(&self, item: &Item) -> io::Result<Vec<u8>> {
+        let mut data = Vec::new();
+
+        for extent in &item.extents {
+            let buf = if extent.in_idat {
+                self.idat.ok_or_else(|| invalid_data("No idat box"))?
+            } else {
+                self.buf
+            };
+
+            let start = usize::try_from(extent.offset)
+                .ok()
+                .filter(|&start| start <= buf.len())
+                .ok_or_else(|| invalid_data("Item extent out of bounds"))?;
+            let end = if extent.length == 0 {
+                buf.len()
+            } else {
+                extent
+                    .offset
+                    .checked_add(extent.length)
+                    .and_then(|end| usize::try_from(end).ok())
+                    .filter(|&end| end <= buf.len())
+                    .ok_or_else(|| invalid_data("Item extent out of bounds"))?
+            };
+
+            data.extend_from_slice(&buf[start..end]);
+        }
+
+        Ok(data)
+    }
+
+    /// Decodes the primary image and its alpha channel.
+    pub fn decode(&self) -> Result<Image, AvifError> {
+        let color = decode_item(self.item_data(&self.primary)?)?;
+        let alpha = match &self.alpha {
+            Some(alpha) => Some(decode_item(self.item_data(alpha)?)?),
+            None => None,
+        };
+
+        Ok(Image {
+            color,
+            alpha,
+            premultiplied_alpha: self.premultiplied_alpha,
+            properties: self.primary.properties.clone(),
+        })
+    }
+}
+
+fn decode_item(data: Vec<u8>) -> Result<Picture, Error> {
+    let mut settings = Settings::new();
+    settings.set_max_frame_delay(1);
+    let mut dec = Decoder::with_settings(&settings)?;
+
+    match dec.send_data(data, None, None, None) {
+        Err(e) if e.is_again() => (),
+        Err(e) => return Err(e),
+        Ok(()) => (),
+    }
+
+    dec.get_picture()
+}
+
+fn take_by_id<T>(items: &mut Vec<(u32, T)>, id: u32) -> Option<T> {
+    let pos = items.iter().position(|(item_id, _)| *item_id == id)?;
+    Some(items.swap_remove(pos).1)
+}
+
+/// Returns the ID and type of all items.
+fn parse_iinf(meta: &[u8]) -> io::Result<Vec<(u32, [u8; 4])>> {
+    let iinf = find_box(meta, b"iinf")?.ok_or_else(|| invalid_data("No item info"))?;
+    let mut r = ByteReader::new(iinf);
+    let (version, _) = r.full_box_header()?;
+    r.skip(if version == 0 { 2 } else { 4 })?;
+
+    let mut items = Vec::new();
+    for_each_box(r.rest(), |box_type, infe| {
+        if &box_type != b"infe" {
+            return Ok(());
+        }
+
+        let mut r = ByteReader::new(infe);
+        let (version, _) = r.full_box_header()?;
+        if version < 2 {
+            return Err(invalid_data("Unsupported item info entry version"));
+        }
+        let id = read_item_id(&mut r, version != 2)?;
+        r.skip(2)?;
+        let item_type = r.bytes(4)?;
+
+        items.push((id, [item_type[0], item_type[1], item_type[2], item_type[3]]));
+        Ok(())
+    })?;
+
+    Ok(items)
+}
+
+/// Returns the extents of all items.
+fn parse_iloc(meta: &[u8]) -> io::Result<Vec<(u32, Vec<Extent>)>> {
+    let iloc = find_box(meta, b"iloc")?.ok_or_else(|| invalid_data("No item locations"))?;
+    let mut r = ByteReader::new(iloc);
+    let (version, _) = r.full_box_header()?;
+    if version > 2 {
+        return Err(invalid_data("Unsupported item location version"));
+    }
+
+    let sizes = r.u16()?;
+    let offset_size = usize::from(sizes >> 12);
+    let length_size = usize::from((sizes >> 8) & 0xf);
+    let base_offset_size = usize::from((sizes >> 4) & 0xf);
+    let index_size = if version > 0 {
+        usize::from(sizes & 0xf)
+    } else {
+        0
+    };
+    for size in [offset_size, length_size, base_offset_size, index_size] {
+        if ![0, 4, 8].contains(&size) {
+            return Err(invalid_data("Invalid item location field size"));
+        }
+    }
+
+    let item_count = if version < 2 {
+        u32::from(r.u16()?)
+    } else {
+        r.u32()?
+    };
+
+    let mut items = Vec::new();
+    for _ in 0..item_count {
+        let id = read_item_id(&mut r, version == 2)?;
+        let construction_method = if version > 0 { r.u16()? & 0xf } else { 0 };
+        if construction_method > 1 {
+            return Err(invalid_data("Unsupported item construction method"));
+        }
+        r.skip(2)?;
+        let base_offset = r.uint(base_offset_size)?;
+
+        let extent_count = r.u16()?;
+        let mut extents = Vec::new();
+        for _ in 0..extent_count {
+            r.skip(index_size)?;
+            let offset = r.uint(offset_size)?;
+            let length = r.uint(length_size)?;
+            extents.push(Extent {
+                in_idat: construction_method == 1,
+                offset: base_offset
+                    .checked_add(offset)
+                    .ok_or_else(|| invalid_data("Invalid item extent offset"))?,
+                length,
+            });
+        }
+
+        items.push((id, extents));
+    }
+
+    Ok(items)
+}
+
+/// Returns the properties of all items.
+fn parse_iprp(meta: &[u8]) -> io::Result<Vec<(u32, ItemProperties)>> {
+    let iprp = match find_box(meta, b"iprp")? {
+        Some(iprp) => iprp,
+        None => return Ok(Vec::new()),
+    };
+
+    let mut properties = Vec::new();
+    if let Some(ipco) = find_box(iprp, b"ipco")? {
+        for_each_box(ipco, |box_type, data| {
+            properties.push((box_type, data));
+            Ok(())
+        })?;
+    }
+
+    let mut items = Vec::new();
+    for_each_box(iprp, |box_type, ipma| {
+        if &box_type != b"ipma" {
+            return Ok(());
+        }
+
+        let mut r = ByteReader::new(ipma);
+        let (version, flags) = r.full_box_header()?;
+        for _ in 0..r.u32()? {
+            let id = read_item_id(&mut r, version != 0)?;
+
+            let mut item_properties = ItemProperties::default();
+            for _ in 0..r.u8()? {
+                // The essential bit is ignored, all properties relevant for decoding are handled
+                let index = if flags & 1 != 0 {
+                    usize::from(r.u16()? & 0x7fff)
+                } else {
+                    usize::from(r.u8()? & 0x7f)
+                };
+
+                // Index 0 means no property
+                if let Some(&(box_type, data)) =
+                    index.checked_sub(1).and_then(|i| properties.get(i))
+                {
+                    parse_property(&mut item_properties, box_type, data)?;
+                }
+            }
+
+            items.push((id, item_properties));
+        }
+
+        Ok(())
+    })?;
+
+    Ok(items)
+}
+
+fn parse_property(
+    properties: &mut ItemProperties,
+    box_type: [u8; 4],
+    data: &[u8],
+) -> io::Result<()> {
+    let mut r = ByteReader::new(data);
+
+    match &box_type {
+        b"ispe" => {
+            r.full_box_header()?;
+            properties.size = Some((r.u32()?, r.u32()?));
+        }
+        b"pixi" => {
+            r.full_box_header()?;
+            let num_channels = usize::from(r.u8()?);
+            properties.bits_per_channel = Some(r.bytes(num_channels)?.to_vec());
+        }
+        b"av1C" => {
+            properties.codec_configuration = Some(Av1CodecConfiguration::parse(data)?);
+        }
+        b"colr" => match r.bytes(4)? {
+            b"nclx" => {
+                properties.nclx = Some(Nclx {
+                    color_primaries: r.u16()?,
+                    transfer_characteristics: r.u16()?,
+                    matrix_coefficients: r.u16()?,
+                    full_range: r.u8()? & 0x80 != 0,
+                });
+            }
+            b"rICC" | b"prof" => properties.icc_profile = Some(r.rest().to_vec()),
+            _ => (),
+        },
+        b"clap" => {
+            properties.clean_aperture = Some(CleanAperture {
+                width_n: r.u32()?,
+                width_d: r.u32()?,
+                height_n: r.u32()?,
+                height_d: r.u32()?,
+                horiz_off_n: r.u32()? as i32,
+                horiz_off_d: r.u32()?,
+                vert_off_n: r.u32()? as i32,
+                vert_off_d: r.u32()?,
+            });
+        }
+        b"irot" => properties.rotation = Some(u16::from(r.u8()? & 0x3) * 90),
+        b"imir" => {
+            properties.mirror = Some(if r.u8()? & 0x1 == 0 {
+                MirrorAxis::Vertical
+            } else {
+                MirrorAxis::Horizontal
+            });
+        }
+        b"auxC" => {
+            r.full_box_header()?;
+            let aux_type = r.rest();
+            let end = aux_type
+                .iter()
+                .position(|&b| b == 0)
+                .unwrap_or(aux_type.len());
+            properties.auxiliary_type =
+                Some(String::from_utf8_lossy(&aux_type[..end]).into_owned());
+        }
+        _ => (),
+    }
+
+    Ok(())
+}
+
+#[derive(Debug)]
+struct ItemReference {
+    reference_type: [u8; 4],
+    from_id: u32,
+    to_ids: Vec<u32>,
+}
+
+fn parse_iref(iref: &[u8]) -> io::Result<Vec<ItemReference>> {
+    let mut r = ByteReader::new(iref);
+    let (version, _) = r.full_box_header()?;
+
+    let mut references = Vec::new();
+    for_each_box(r.rest(), |reference_type, data| {
+        let mut r = ByteReader::new(data);
+        let from_id = read_item_id(&mut r, version != 0)?;
+        let to_ids = (0..r.u16()?)
+            .map(|_| read_item_id(&mut r, version != 0))
+            .collect::<io::Result<Vec<_>>>()?;
+
+        references.push(ItemReference {
+            reference_type,
+            from_id,
+            to_ids,
+        });
+        Ok(())
+    })?;
+
+    Ok(references)
+}
+
+/// Reads a 16 bit or, if `large` is set, 32 bit item ID.
+fn read_item_id(r: &mut ByteReader, large: bool) -> io::Result<u32> {
+    if large {
+        r.u32()
+    } else {
+        r.u16().map(u32::from)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bmff::test::{full_box, mp4_box, u32s};
+    use crate::ivf;
+
+    static TEST_FILE_420_8: &[u8] = include_bytes!("../test-420-8.ivf");
+
+    const AV1C: [u8; 4] = [0x81, 0x00, 0x0c, 0x00];
+
+    fn u16s(values: &[u16]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_be_bytes()).collect()
+    }
+
+    fn keyframe() -> Vec<u8> {
+        let mut r = ivf::Reader::new(TEST_FILE_420_8).unwrap();
+        r.read_packet().unwrap().unwrap().data.into_vec()
+    }
+
+    // Creates an AVIF file with the first frame of the test file as color and alpha image. The
+    // color image is stored in the `mdat` box, the alpha image in the `idat` box.
+    fn make_avif(brands: &[u8]) -> Vec<u8> {
+        let data = keyframe();
+        let ftyp = mp4_box(b"ftyp", brands);
+
+        let meta = |color_offset: u32| {
+            let hdlr = full_box(b"hdlr", 0, 0, &[&[0; 4][..], b"pict", &[0; 13]].concat());
+            let pitm = full_box(b"pitm", 0, 0, &u16s(&[1]));
+
+            let infe = |id: u16| {
+                full_box(
+                    b"infe",
+                    2,
+                    0,
+                    &[u16s(&[id, 0]), b"av01\0".to_vec()].concat(),
+                )
+            };
+            let iinf = full_box(b"iinf", 0, 0, &[u16s(&[2]), infe(1), infe(2)].concat());
+
+            let iloc = full_box(
+                b"iloc",
+                1,
+                0,
+                &[
+                    u16s(&[0x4400, 2]),
+                    u16s(&[1, 0, 0, 1]),
+                    u32s(&[color_offset, data.len() as u32]),
+                    u16s(&[2, 1, 0, 1]),
+                    u32s(&[0, data.len() as u32]),
+                ]
+                .concat(),
+            );
+
+            let ipco = mp4_box(
+                b"ipco",
+                &[
+                    full_box(b"ispe", 0, 0, &u32s(&[320, 240])),
+                    full_box(b"pixi", 0, 0, &[3, 8, 8, 8]),
+                    mp4_box(b"av1C", &AV1C),
+                    mp4_box(
+                        b"colr",
+                        &[b"nclx".to_vec(), u16s(&[1, 13, 6]), vec![0x80]].concat(),
+                    ),
+                    mp4_box(b"clap", &u32s(&[300, 1, 200, 1, (-2i32) as u32, 1, 0, 1])),
+                    mp4_box(b"irot", &[1]),
+                    mp4_box(b"imir", &[1]),
+                    full_box(
+                        b"auxC",
+                        0,
+                        0,
+                        b"urn:mpeg:mpegB:cicp:systems:auxiliary:alpha\0",
+                    ),
+                    full_box(b"pixi", 0, 0, &[1, 8]),
+                ]
+                .concat(),
+            );
+            let ipma = full_box(
+                b"ipma",
+                0,
+                0,
+                &[
+                    u32s(&[2]),
+                    u16s(&[1]),
+                    vec![7, 1, 2, 0x83, 4, 5, 6, 7],
+                    u16s(&[2]),
+                    vec![4, 1, 0x83, 8, 9],
+                ]
+                .concat(),
+            );
+            let iprp = mp4_box(b"iprp", &[ipco, ipma].concat());
+
+            let iref = full_box(
+                b"iref",
+                0,
+                0,
+                &[
+                    mp4_box(b"auxl", &u16s(&[2, 1, 1])),
+                    mp4_box(b"prem", &u16s(&[1, 1, 2])),
+                ]
+                .concat(),
+            );
+            let idat = mp4_box(b"idat", &data);
+
+            full_box(
+                b"meta",
+                0,
+                0,
+                &[hdlr, pitm, iinf, iloc, iprp, iref, idat].concat(),
+            )
+        };
+
+        let color_offset = (ftyp.len() + meta(0).len() + 8) as u32;
+        [ftyp, meta(color_offset), mp4_box(b"mdat", &data)].concat()
+    }
+
+    #[test]
+    fn test_parse() {
+        let file = make_avif(b"avif\0\0\0\0mif1miaf");
+        let container = Container::parse(&file).unwrap();
+
+        assert_eq!(container.primary.id, 1);
+        assert_eq!(
+            container.primary.properties,
+            ItemProperties {
+                size: Some((320, 240)),
+                bits_per_channel: Some(vec![8, 8, 8]),
+                codec_configuration: Some(Av1CodecConfiguration::parse(&AV1C).unwrap()),
+                nclx: Some(Nclx {
+                    color_primaries: 1,
+                    transfer_characteristics: 13,
+                    matrix_coefficients: 6,
+                    full_range: true,
+                }),
+                icc_profile: None,
+                clean_aperture: Some(CleanAperture {
+                    width_n: 300,
+                    width_d: 1,
+                    height_n: 200,
+                    height_d: 1,
+                    horiz_off_n: -2,
+                    horiz_off_d: 1,
+                    vert_off_n: 0,
+                    vert_off_d: 1,
+                }),
+                rotation: Some(90),
+                mirror: Some(MirrorAxis::Horizontal),
+                auxiliary_type: None,
+            }
+        );
+
+        let alpha = container.alpha.as_ref().unwrap();
+        assert_eq!(alpha.id, 2);
+        assert_eq!(alpha.properties.bits_per_channel, Some(vec![8]));
+        assert!(container.premultiplied_alpha);
+
+        let data = keyframe();
+        assert_eq!(container.item_data(&container.primary).unwrap(), data);
+        assert_eq!(container.item_data(alpha).unwrap(), data);
+
+        // Extent with a length reaching beyond the address space
+        let mut item = container.primary.clone();
+        item.extents[0].length = u64::MAX;
+        let err = container.item_data(&item).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_not_avif() {
+        let file = make_avif(b"mif1\0\0\0\0mif1");
+        let err = Container::parse(&file).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_decode() {
+        let file = make_avif(b"avif\0\0\0\0mif1miaf");
+        let image = decode(&file).unwrap();
+
+        assert_eq!(image.color.width(), 320);
+        assert_eq!(image.color.height(), 240);
+        let alpha = image.alpha.unwrap();
+        assert_eq!(alpha.width(), 320);
+        assert_eq!(alpha.height(), 240);
+        assert!(image.premultiplied_alpha);
+        assert_eq!(image.properties.rotation, Some(90));
+    }
+}
@@ -0,0 +1,202 @@
+//! Helpers for parsing ISO base media file format boxes.
+
+use std::io::{self, Read};
+
+/// Header of a box.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct BoxHeader {
+    pub(crate) box_type: [u8; 4],
+    // Size of the box content or `None` if the box extends to the end of the file
+    pub(crate) size: Option<u64>,
+}
+
+/// Reads a box header, or returns `None` at the end of the input.
+pub(crate) fn read_box_header(reader: &mut impl Read) -> io::Result<Option<BoxHeader>> {
+    let mut buf = [0u8; 8];
+    match reader.read(&mut buf[..1])? {
+        0 => return Ok(None),
+        _ => reader.read_exact(&mut buf[1..])?,
+    }
+
+    let size = u32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]);
+    let box_type = [buf[4], buf[5], buf[6], buf[7]];
+
+    let (size, header_len) = match size {
+        0 => (None, 8),
+        1 => {
+            let mut large_size = [0u8; 8];
+            reader.read_exact(&mut large_size)?;
+            (Some(u64::from_be_bytes(large_size)), 16)
+        }
+        size => (Some(u64::from(size)), 8),
+    };
+
+    let mut header_len = header_len;
+    if &box_type == b"uuid" {
+        reader.read_exact(&mut [0u8; 16])?;
+        header_len += 16;
+    }
+
+    let size = match size {
+        Some(size) if size < header_len => return Err(invalid_data("Invalid box size")),
+        Some(size) => Some(size - header_len),
+        None => None,
+    };
+
+    Ok(Some(BoxHeader { box_type, size }))
+}
+
+/// Reads the content of a box into memory.
+#[cfg(feature = "mp4")]
+pub(crate) fn read_box_data(reader: &mut impl Read, header: BoxHeader) -> io::Result<Vec<u8>> {
+    // Don't trust the size for allocating everything upfront
+    let mut data = Vec::new();
+    match header.size {
+        Some(size) => {
+            let read = reader.take(size).read_to_end(&mut data)?;
+            if (read as u64) < size {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "Truncated box",
+                ));
+            }
+        }
+        None => {
+            reader.read_to_end(&mut data)?;
+        }
+    }
+
+    Ok(data)
+}
+
+/// Calls `f` with the type and content of every box in `buf`.
+pub(crate) fn for_each_box<'a>(
+    mut buf: &'a [u8],
+    mut f: impl FnMut([u8; 4], &'a [u8]) -> io::Result<()>,
+) -> io::Result<()> {
+    while !buf.is_empty() {
+        let header =
+            read_box_header(&mut buf)?.ok_or_else(|| invalid_data("Truncated box header"))?;
+        let size = match header.size {
+            Some(size) if size > buf.len() as u64 => return Err(invalid_data("Truncated box")),
+            Some(size) => size as usize,
+            None => buf.len(),
+        };
+
+        let (data, rest) = buf.split_at(size);
+        f(header.box_type, data)?;
+        buf = rest;
+    }
+
+    Ok(())
+}
+
+/// Returns the first box of type `box_type` in `buf`.
+pub(crate) fn find_box<'a>(buf: &'a [u8], box_type: &[u8; 4]) -> io::Result<Option<&'a [u8]>> {
+    let mut found = None;
+    for_each_box(buf, |t, data| {
+        if found.is_none() && &t == box_type {
+            found = Some(data);
+        }
+        Ok(())
+    })?;
+
+    Ok(found)
+}
+
+/// Big endian reader over the content of a box.
+#[derive(Debug, Clone)]
+pub(crate) struct ByteReader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> ByteReader<'a> {
+    pub(crate) fn new(buf: &'a [u8]) -> Self {
+        ByteReader { buf }
+    }
+
+    /// Reads the version and flags of a full box.
+    pub(crate) fn full_box_header(&mut self) -> io::Result<(u8, u32)> {
+        let v = self.u32()?;
+        Ok(((v >> 24) as u8, v & 0xff_ffff))
+    }
+
+    pub(crate) fn bytes(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        if len > self.buf.len() {
+            return Err(invalid_data("Truncated box"));
+        }
+
+        let (bytes, rest) = self.buf.split_at(len);
+        self.buf = rest;
+        Ok(bytes)
+    }
+
+    pub(crate) fn skip(&mut self, len: usize) -> io::Result<()> {
+        self.bytes(len).map(|_| ())
+    }
+
+    pub(crate) fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub(crate) fn u16(&mut self) -> io::Result<u16> {
+        let b = self.bytes(2)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    pub(crate) fn u32(&mut self) -> io::Result<u32> {
+        let b = self.bytes(4)?;
+        Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    #[cfg(feature = "mp4")]
+    pub(crate) fn u64(&mut self) -> io::Result<u64> {
+        let b = self.bytes(8)?;
+        Ok(u64::from_be_bytes([
+            b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7],
+        ]))
+    }
+
+    /// Reads an unsigned integer of `len` bytes, with `len` being at most 8.
+    pub(crate) fn uint(&mut self, len: usize) -> io::Result<u64> {
+        debug_assert!(len <= 8);
+        Ok(self
+            .bytes(len)?
+            .iter()
+            .fold(0, |v, &b| (v << 8) | u64::from(b)))
+    }
+
+    /// Remaining unread data.
+    pub(crate) fn rest(&self) -> &'a [u8] {
+        self.buf
+    }
+}
+
+pub(crate) fn invalid_data<E: Into<Box<dyn std::error::Error + Send + Sync>>>(
+    error: E,
+) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    /// Creates a box of type `box_type` with content `data`.
+    pub(crate) fn mp4_box(box_type: &[u8; 4], data: &[u8]) -> Vec<u8> {
+        let mut out = ((data.len() + 8) as u32).to_be_bytes().to_vec();
+        out.extend_from_slice(box_type);
+        out.extend_from_slice(data);
+        out
+    }
+
+    /// Creates a full box, i.e. a box with version and flags before the content.
+    pub(crate) fn full_box(box_type: &[u8; 4], version: u8, flags: u32, data: &[u8]) -> Vec<u8> {
+        let mut content = (u32::from(version) << 24 | flags).to_be_bytes().to_vec();
+        content.extend_from_slice(data);
+        mp4_box(box_type, &content)
+    }
+
+    /// Big endian bytes of `values`.
+    pub(crate) fn u32s(values: &[u32]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_be_bytes()).collect()
+    }
+}
@@ -0,0 +1,198 @@
+//! Export of pictures as RGB images.
+//!
+//! Pictures are converted to RGB according to their matrix coefficients, color range and chroma
+//! sample position. Monochrome pictures are exported as grayscale images.
+
+use crate::rgb::{self, Source};
+use crate::{Picture, PictureAllocator, PixelLayout, PlanarImageComponent};
+use std::io::{self, Write};
+
+/// Sample depth of exported images.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Depth {
+    /// 8 bits per sample.
+    Eight,
+    /// 16 bits per sample.
+    Sixteen,
+}
+
+impl Depth {
+    /// Depth that keeps the precision of `picture`, i.e. 8 bits for 8 bit pictures and 16 bits
+    /// otherwise.
+    pub fn for_picture<A: PictureAllocator>(picture: &Picture<A>) -> Self {
+        if picture.bit_depth() > 8 {
+            Depth::Sixteen
+        } else {
+            Depth::Eight
+        }
+    }
+}
+
+/// Converts the picture to interleaved RGB or grayscale samples, with 16 bit samples stored as
+/// big endian.
+fn to_samples<A: PictureAllocator>(picture: &Picture<A>, depth: Depth) -> Vec<u8> {
+    let planes = [
+        picture.plane(PlanarImageComponent::Y),
+        picture.plane(PlanarImageComponent::U),
+        picture.plane(PlanarImageComponent::V),
+    ];
+    let source = Source::from_picture(picture, &planes);
+    let channels = if source.chroma.is_none() { 1 } else { 3 };
+
+    let mut data = Vec::with_capacity(
+        source.luma.width
+            * source.luma.height
+            * channels
+            * match depth {
+                Depth::Eight => 1,
+                Depth::Sixteen => 2,
+            },
+    );
+    rgb::convert(&source, |row| {
+        for pixel in row {
+            for &v in &pixel[..channels] {
+                match depth {
+                    Depth::Eight => data.push(rgb::to_u8(v)),
+                    Depth::Sixteen => data.extend(rgb::to_u16(v).to_be_bytes()),
+                }
+            }
+        }
+    });
+
+    data
+}
+
+/// Writes the picture as binary PPM image, or as PGM image for monochrome pictures.
+pub fn write_pnm<A: PictureAllocator, W: Write>(
+    picture: &Picture<A>,
+    mut writer: W,
+    depth: Depth,
+) -> io::Result<()> {
+    write!(
+        writer,
+        "{}\n{} {}\n{}\n",
+        if picture.pixel_layout() == PixelLayout::I400 {
+            "P5"
+        } else {
+            "P6"
+        },
+        picture.width(),
+        picture.height(),
+        match depth {
+            Depth::Eight => 255,
+            Depth::Sixteen => 65535,
+        },
+    )?;
+    writer.write_all(&to_samples(picture, depth))?;
+    writer.flush()
+}
+
+/// Writes the picture as PNG image.
+#[cfg(feature = "png")]
+pub fn write_png<A: PictureAllocator, W: Write>(
+    picture: &Picture<A>,
+    writer: W,
+    depth: Depth,
+) -> io::Result<()> {
+    let mut encoder = png::Encoder::new(writer, picture.width(), picture.height());
+    encoder.set_color(if picture.pixel_layout() == PixelLayout::I400 {
+        png::ColorType::Grayscale
+    } else {
+        png::ColorType::Rgb
+    });
+    encoder.set_depth(match depth {
+        Depth::Eight => png::BitDepth::Eight,
+        Depth::Sixteen => png::BitDepth::Sixteen,
+    });
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&to_samples(picture, depth))?;
+    writer.finish()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Decoder, RgbFormat};
+
+    static TEST_FILE_420_8: &[u8] = include_bytes!("../test-420-8.ivf");
+    static TEST_FILE_420_12: &[u8] = include_bytes!("../test-420-12.ivf");
+
+    fn decode_first(file: &[u8]) -> Picture {
+        let mut pictures = Vec::new();
+        crate::test::decode_file(file, Decoder::new().unwrap(), &mut pictures);
+        pictures.remove(0)
+    }
+
+    #[test]
+    fn test_pnm() {
+        for (file, depth, header, len) in [
+            (
+                TEST_FILE_420_8,
+                Depth::Eight,
+                "P6\n320 240\n255\n",
+                320 * 240 * 3,
+            ),
+            (
+                TEST_FILE_420_12,
+                Depth::Sixteen,
+                "P6\n320 240\n65535\n",
+                320 * 240 * 6,
+            ),
+        ] {
+            let picture = decode_first(file);
+            assert_eq!(Depth::for_picture(&picture), depth);
+
+            let mut pnm = Vec::new();
+            write_pnm(&picture, &mut pnm, depth).unwrap();
+            assert!(pnm.starts_with(header.as_bytes()));
+            assert_eq!(pnm.len(), header.len() + len);
+
+            // Same colors as the RGB conversion, with 16 bit samples stored as big endian
+            let samples = &pnm[header.len()..];
+            match depth {
+                Depth::Eight => {
+                    let mut rgb = vec![0; 320 * 240 * 3];
+                    picture.to_rgb(&mut rgb, 320 * 3, RgbFormat::Rgb24).unwrap();
+                    assert_eq!(samples, &rgb[..]);
+                }
+                Depth::Sixteen => {
+                    let mut rgb = vec![0; 320 * 240 * 6];
+                    picture.to_rgb(&mut rgb, 320 * 6, RgbFormat::Rgb48).unwrap();
+                    for (sample, rgb) in samples.chunks_exact(2).zip(rgb.chunks_exact(2)) {
+                        assert_eq!(
+                            u16::from_be_bytes([sample[0], sample[1]]),
+                            u16::from_ne_bytes([rgb[0], rgb[1]])
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn test_png() {
+        for (file, depth) in [
+            (TEST_FILE_420_8, Depth::Eight),
+            (TEST_FILE_420_12, Depth::Sixteen),
+        ] {
+            let picture = decode_first(file);
+            let mut pnm = Vec::new();
+            write_pnm(&picture, &mut pnm, depth).unwrap();
+            let mut png = Vec::new();
+            write_png(&picture, &mut png, depth).unwrap();
+
+            let mut reader = png::Decoder::new(&png[..]).read_info().unwrap();
+            let mut data = vec![0; reader.output_buffer_size()];
+            let info = reader.next_frame(&mut data).unwrap();
+            assert_eq!((info.width, info.height), (320, 240));
+            assert_eq!(info.color_type, png::ColorType::Rgb);
+
+            // Same samples as the PPM image
+            assert!(pnm.ends_with(&data[..info.buffer_size()]));
+        }
+    }
+}
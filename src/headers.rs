@@ -0,0 +1,979 @@
+//! Owned copies of the AV1 headers parsed by `dav1d`.
+
+use crate::{
+    bits_per_component, chroma_location, color_primaries, color_range, matrix_coefficients, pixel,
+    pixel_layout, transfer_characteristic, BitsPerComponent, PixelLayout, TryFromEnumError,
+};
+use dav1d_sys::*;
+
+/// Tri-state flag of a coding tool that can also be selected per frame.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AdaptiveBoolean {
+    /// The tool is disabled for the whole sequence.
+    Off,
+    /// The tool is enabled for the whole sequence.
+    On,
+    /// The tool is signalled in every frame header.
+    Adaptive,
+}
+
+impl TryFrom<u32> for AdaptiveBoolean {
+    type Error = TryFromEnumError;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            DAV1D_OFF => Ok(AdaptiveBoolean::Off),
+            DAV1D_ON => Ok(AdaptiveBoolean::On),
+            DAV1D_ADAPTIVE => Ok(AdaptiveBoolean::Adaptive),
+            _ => Err(TryFromEnumError(())),
+        }
+    }
+}
+
+impl From<AdaptiveBoolean> for u32 {
+    fn from(v: AdaptiveBoolean) -> u32 {
+        match v {
+            AdaptiveBoolean::Off => DAV1D_OFF,
+            AdaptiveBoolean::On => DAV1D_ON,
+            AdaptiveBoolean::Adaptive => DAV1D_ADAPTIVE,
+        }
+    }
+}
+
+/// Timing information of a sequence.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TimingInfo {
+    /// Number of time units of a clock operating at `time_scale` Hz per tick.
+    pub num_units_in_tick: u32,
+    /// Number of time units that pass in one second.
+    pub time_scale: u32,
+    /// Number of ticks per picture if all pictures have the same duration.
+    pub num_ticks_per_picture: Option<u32>,
+}
+
+/// Decoder model information of a sequence.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DecoderModelInfo {
+    /// Length in bits of the decoder and encoder buffer delay fields.
+    pub encoder_decoder_buffer_delay_length: u8,
+    /// Number of time units of a decoding clock tick.
+    pub num_units_in_decoding_tick: u32,
+    /// Length in bits of the buffer removal time fields of the frame headers.
+    pub buffer_removal_delay_length: u8,
+    /// Length in bits of the frame presentation time fields of the frame headers.
+    pub frame_presentation_delay_length: u8,
+}
+
+/// Decoder model parameters of an operating point.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct OperatingParameterInfo {
+    /// Decoder buffer delay in units of the decoding clock.
+    pub decoder_buffer_delay: u32,
+    /// Encoder buffer delay in units of the decoding clock.
+    pub encoder_buffer_delay: u32,
+    /// Whether the decoder model operates in low delay mode.
+    pub low_delay_mode: bool,
+}
+
+/// An operating point of a sequence.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct OperatingPoint {
+    /// Major part of the level, e.g. `5` for level 5.1.
+    pub major_level: u8,
+    /// Minor part of the level, e.g. `1` for level 5.1.
+    pub minor_level: u8,
+    /// Number of decoded frames to buffer before display.
+    pub initial_display_delay: u8,
+    /// Temporal (lower 8 bits) and spatial (upper 4 bits) layers decoded for this operating point.
+    pub idc: u16,
+    /// Tier of the operating point, `0` for Main and `1` for High.
+    pub tier: u8,
+    /// Whether the initial display delay was signalled for this operating point.
+    pub display_model_param_present: bool,
+    /// Decoder model parameters if signalled for this operating point.
+    pub decoder_model_params: Option<OperatingParameterInfo>,
+}
+
+impl OperatingPoint {
+    /// The `seq_level_idx` as coded in the bitstream.
+    ///
+    /// Major levels below 2 don't exist and are treated as level 2.
+    pub fn seq_level_idx(&self) -> u8 {
+        (self.major_level.saturating_sub(2) << 2) | self.minor_level
+    }
+}
+
+/// An AV1 sequence header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SequenceHeader {
+    /// Stream profile.
+    ///
+    /// `0` for 8-10 bits 4:2:0 or monochrome, `1` for 8-10 bits 4:4:4 and `2` for 4:2:2 or
+    /// 12 bits.
+    pub profile: u8,
+    /// Maximum width of the frames in the sequence.
+    pub max_width: u32,
+    /// Maximum height of the frames in the sequence.
+    pub max_height: u32,
+    /// Pixel layout of the frames.
+    pub pixel_layout: PixelLayout,
+    /// Chromaticity coordinates of the source colour primaries.
+    pub color_primaries: pixel::ColorPrimaries,
+    /// Transfer characteristics function.
+    pub transfer_characteristic: pixel::TransferCharacteristic,
+    /// Matrix coefficients used in deriving luma and chroma signals.
+    pub matrix_coefficients: pixel::MatrixCoefficients,
+    /// Sample position for subsampled chroma.
+    pub chroma_location: pixel::ChromaLocation,
+    /// Bits used per component.
+    pub bits_per_component: Option<BitsPerComponent>,
+    /// YUV color range.
+    pub color_range: pixel::YUVRange,
+    /// Operating points of the sequence.
+    pub operating_points: Vec<OperatingPoint>,
+    /// The sequence contains a single still picture.
+    pub still_picture: bool,
+    /// The sequence uses the reduced still picture header.
+    pub reduced_still_picture_header: bool,
+    /// Timing information, if present.
+    pub timing_info: Option<TimingInfo>,
+    /// Decoder model information, if present.
+    pub decoder_model_info: Option<DecoderModelInfo>,
+    /// Whether display model information is present.
+    pub display_model_info_present: bool,
+    /// Number of bits used to code the frame width.
+    pub width_n_bits: u8,
+    /// Number of bits used to code the frame height.
+    pub height_n_bits: u8,
+    /// Whether frame ids are present in the frame headers.
+    pub frame_id_numbers_present: bool,
+    /// Number of bits used to code delta frame ids.
+    pub delta_frame_id_n_bits: u8,
+    /// Number of bits used to code frame ids.
+    pub frame_id_n_bits: u8,
+    /// Superblocks are 128x128 instead of 64x64.
+    pub sb128: bool,
+    /// Filter intra prediction is allowed.
+    pub filter_intra: bool,
+    /// Intra edge filtering is enabled.
+    pub intra_edge_filter: bool,
+    /// Inter-intra compound prediction is allowed.
+    pub inter_intra: bool,
+    /// Masked compound prediction is allowed.
+    pub masked_compound: bool,
+    /// Warped motion is allowed.
+    pub warped_motion: bool,
+    /// Separate horizontal and vertical interpolation filters are allowed.
+    pub dual_filter: bool,
+    /// Order hints are used.
+    pub order_hint: bool,
+    /// Distance weighted compound prediction is allowed.
+    pub jnt_comp: bool,
+    /// Motion vectors from reference frames are used.
+    pub ref_frame_mvs: bool,
+    /// Screen content tools.
+    pub screen_content_tools: AdaptiveBoolean,
+    /// Integer motion vectors.
+    pub force_integer_mv: AdaptiveBoolean,
+    /// Number of bits used for order hints.
+    pub order_hint_n_bits: u8,
+    /// Superresolution is allowed.
+    pub super_res: bool,
+    /// CDEF filtering is enabled.
+    pub cdef: bool,
+    /// Loop restoration filtering is enabled.
+    pub restoration: bool,
+    /// Horizontal chroma subsampling shift.
+    pub ss_hor: u8,
+    /// Vertical chroma subsampling shift.
+    pub ss_ver: u8,
+    /// The sequence is monochrome.
+    pub monochrome: bool,
+    /// Whether the color description was signalled.
+    pub color_description_present: bool,
+    /// U and V planes use separate delta quantizers.
+    pub separate_uv_delta_q: bool,
+    /// Film grain parameters are present in the frame headers.
+    pub film_grain_present: bool,
+}
+
+impl SequenceHeader {
+    pub(crate) fn from_raw(hdr: &Dav1dSequenceHeader) -> Self {
+        let operating_points = hdr.operating_points[..hdr.num_operating_points as usize]
+            .iter()
+            .zip(hdr.operating_parameter_info.iter())
+            .map(|(op, info)| OperatingPoint {
+                major_level: op.major_level,
+                minor_level: op.minor_level,
+                initial_display_delay: op.initial_display_delay,
+                idc: op.idc,
+                tier: op.tier,
+                display_model_param_present: op.display_model_param_present != 0,
+                decoder_model_params: (op.decoder_model_param_present != 0).then_some(
+                    OperatingParameterInfo {
+                        decoder_buffer_delay: info.decoder_buffer_delay,
+                        encoder_buffer_delay: info.encoder_buffer_delay,
+                        low_delay_mode: info.low_delay_mode != 0,
+                    },
+                ),
+            })
+            .collect();
+
+        SequenceHeader {
+            profile: hdr.profile,
+            max_width: hdr.max_width as u32,
+            max_height: hdr.max_height as u32,
+            pixel_layout: pixel_layout(hdr.layout),
+            color_primaries: color_primaries(hdr.pri),
+            transfer_characteristic: transfer_characteristic(hdr.trc),
+            matrix_coefficients: matrix_coefficients(hdr.mtrx),
+            chroma_location: chroma_location(hdr.chr),
+            bits_per_component: bits_per_component(hdr.hbd),
+            color_range: color_range(hdr.color_range),
+            operating_points,
+            still_picture: hdr.still_picture != 0,
+            reduced_still_picture_header: hdr.reduced_still_picture_header != 0,
+            timing_info: (hdr.timing_info_present != 0).then_some(TimingInfo {
+                num_units_in_tick: hdr.num_units_in_tick,
+                time_scale: hdr.time_scale,
+                num_ticks_per_picture: (hdr.equal_picture_interval != 0)
+                    .then_some(hdr.num_ticks_per_picture),
+            }),
+            decoder_model_info: (hdr.decoder_model_info_present != 0).then_some(DecoderModelInfo {
+                encoder_decoder_buffer_delay_length: hdr.encoder_decoder_buffer_delay_length,
+                num_units_in_decoding_tick: hdr.num_units_in_decoding_tick,
+                buffer_removal_delay_length: hdr.buffer_removal_delay_length,
+                frame_presentation_delay_length: hdr.frame_presentation_delay_length,
+            }),
+            display_model_info_present: hdr.display_model_info_present != 0,
+            width_n_bits: hdr.width_n_bits,
+            height_n_bits: hdr.height_n_bits,
+            frame_id_numbers_present: hdr.frame_id_numbers_present != 0,
+            delta_frame_id_n_bits: hdr.delta_frame_id_n_bits,
+            frame_id_n_bits: hdr.frame_id_n_bits,
+            sb128: hdr.sb128 != 0,
+            filter_intra: hdr.filter_intra != 0,
+            intra_edge_filter: hdr.intra_edge_filter != 0,
+            inter_intra: hdr.inter_intra != 0,
+            masked_compound: hdr.masked_compound != 0,
+            warped_motion: hdr.warped_motion != 0,
+            dual_filter: hdr.dual_filter != 0,
+            order_hint: hdr.order_hint != 0,
+            jnt_comp: hdr.jnt_comp != 0,
+            ref_frame_mvs: hdr.ref_frame_mvs != 0,
+            screen_content_tools: AdaptiveBoolean::try_from(hdr.screen_content_tools)
+                .expect("Invalid Dav1dAdaptiveBoolean"),
+            force_integer_mv: AdaptiveBoolean::try_from(hdr.force_integer_mv)
+                .expect("Invalid Dav1dAdaptiveBoolean"),
+            order_hint_n_bits: hdr.order_hint_n_bits,
+            super_res: hdr.super_res != 0,
+            cdef: hdr.cdef != 0,
+            restoration: hdr.restoration != 0,
+            ss_hor: hdr.ss_hor,
+            ss_ver: hdr.ss_ver,
+            monochrome: hdr.monochrome != 0,
+            color_description_present: hdr.color_description_present != 0,
+            separate_uv_delta_q: hdr.separate_uv_delta_q != 0,
+            film_grain_present: hdr.film_grain_present != 0,
+        }
+    }
+}
+
+/// Type of a frame.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FrameType {
+    /// Key frame.
+    Key,
+    /// Inter frame.
+    Inter,
+    /// Intra-only frame.
+    Intra,
+    /// Switch frame.
+    Switch,
+}
+
+impl FrameType {
+    /// Whether the frame is coded without reference to other frames.
+    pub const fn is_intra(&self) -> bool {
+        matches!(self, FrameType::Key | FrameType::Intra)
+    }
+}
+
+impl TryFrom<u32> for FrameType {
+    type Error = TryFromEnumError;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            DAV1D_FRAME_TYPE_KEY => Ok(FrameType::Key),
+            DAV1D_FRAME_TYPE_INTER => Ok(FrameType::Inter),
+            DAV1D_FRAME_TYPE_INTRA => Ok(FrameType::Intra),
+            DAV1D_FRAME_TYPE_SWITCH => Ok(FrameType::Switch),
+            _ => Err(TryFromEnumError(())),
+        }
+    }
+}
+
+impl From<FrameType> for u32 {
+    fn from(v: FrameType) -> u32 {
+        match v {
+            FrameType::Key => DAV1D_FRAME_TYPE_KEY,
+            FrameType::Inter => DAV1D_FRAME_TYPE_INTER,
+            FrameType::Intra => DAV1D_FRAME_TYPE_INTRA,
+            FrameType::Switch => DAV1D_FRAME_TYPE_SWITCH,
+        }
+    }
+}
+
+/// Interpolation filter used for motion compensation.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FilterMode {
+    /// Regular 8-tap filter.
+    Regular8Tap,
+    /// Smooth 8-tap filter.
+    Smooth8Tap,
+    /// Sharp 8-tap filter.
+    Sharp8Tap,
+    /// Bilinear filter.
+    Bilinear,
+    /// Filter selected per block.
+    Switchable,
+}
+
+impl TryFrom<u32> for FilterMode {
+    type Error = TryFromEnumError;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            DAV1D_FILTER_8TAP_REGULAR => Ok(FilterMode::Regular8Tap),
+            DAV1D_FILTER_8TAP_SMOOTH => Ok(FilterMode::Smooth8Tap),
+            DAV1D_FILTER_8TAP_SHARP => Ok(FilterMode::Sharp8Tap),
+            DAV1D_FILTER_BILINEAR => Ok(FilterMode::Bilinear),
+            DAV1D_FILTER_SWITCHABLE => Ok(FilterMode::Switchable),
+            _ => Err(TryFromEnumError(())),
+        }
+    }
+}
+
+impl From<FilterMode> for u32 {
+    fn from(v: FilterMode) -> u32 {
+        match v {
+            FilterMode::Regular8Tap => DAV1D_FILTER_8TAP_REGULAR,
+            FilterMode::Smooth8Tap => DAV1D_FILTER_8TAP_SMOOTH,
+            FilterMode::Sharp8Tap => DAV1D_FILTER_8TAP_SHARP,
+            FilterMode::Bilinear => DAV1D_FILTER_BILINEAR,
+            FilterMode::Switchable => DAV1D_FILTER_SWITCHABLE,
+        }
+    }
+}
+
+/// Transform size selection mode.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TxfmMode {
+    /// Only 4x4 transforms are used.
+    Only4x4,
+    /// The largest transform size for the block is used.
+    Largest,
+    /// The transform size is selected per block.
+    Switchable,
+}
+
+impl TryFrom<u32> for TxfmMode {
+    type Error = TryFromEnumError;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            DAV1D_TX_4X4_ONLY => Ok(TxfmMode::Only4x4),
+            DAV1D_TX_LARGEST => Ok(TxfmMode::Largest),
+            DAV1D_TX_SWITCHABLE => Ok(TxfmMode::Switchable),
+            _ => Err(TryFromEnumError(())),
+        }
+    }
+}
+
+impl From<TxfmMode> for u32 {
+    fn from(v: TxfmMode) -> u32 {
+        match v {
+            TxfmMode::Only4x4 => DAV1D_TX_4X4_ONLY,
+            TxfmMode::Largest => DAV1D_TX_LARGEST,
+            TxfmMode::Switchable => DAV1D_TX_SWITCHABLE,
+        }
+    }
+}
+
+/// Loop restoration filter type of a plane.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RestorationType {
+    /// No loop restoration.
+    None,
+    /// Filter selected per restoration unit.
+    Switchable,
+    /// Wiener filter.
+    Wiener,
+    /// Self-guided filter.
+    SgrProj,
+}
+
+impl TryFrom<u32> for RestorationType {
+    type Error = TryFromEnumError;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            DAV1D_RESTORATION_NONE => Ok(RestorationType::None),
+            DAV1D_RESTORATION_SWITCHABLE => Ok(RestorationType::Switchable),
+            DAV1D_RESTORATION_WIENER => Ok(RestorationType::Wiener),
+            DAV1D_RESTORATION_SGRPROJ => Ok(RestorationType::SgrProj),
+            _ => Err(TryFromEnumError(())),
+        }
+    }
+}
+
+impl From<RestorationType> for u32 {
+    fn from(v: RestorationType) -> u32 {
+        match v {
+            RestorationType::None => DAV1D_RESTORATION_NONE,
+            RestorationType::Switchable => DAV1D_RESTORATION_SWITCHABLE,
+            RestorationType::Wiener => DAV1D_RESTORATION_WIENER,
+            RestorationType::SgrProj => DAV1D_RESTORATION_SGRPROJ,
+        }
+    }
+}
+
+/// Type of a global motion transformation.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum WarpedMotionType {
+    /// No motion.
+    Identity,
+    /// Translation only.
+    Translation,
+    /// Rotation and zoom.
+    RotZoom,
+    /// Full affine transformation.
+    Affine,
+}
+
+impl TryFrom<u32> for WarpedMotionType {
+    type Error = TryFromEnumError;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            DAV1D_WM_TYPE_IDENTITY => Ok(WarpedMotionType::Identity),
+            DAV1D_WM_TYPE_TRANSLATION => Ok(WarpedMotionType::Translation),
+            DAV1D_WM_TYPE_ROT_ZOOM => Ok(WarpedMotionType::RotZoom),
+            DAV1D_WM_TYPE_AFFINE => Ok(WarpedMotionType::Affine),
+            _ => Err(TryFromEnumError(())),
+        }
+    }
+}
+
+impl From<WarpedMotionType> for u32 {
+    fn from(v: WarpedMotionType) -> u32 {
+        match v {
+            WarpedMotionType::Identity => DAV1D_WM_TYPE_IDENTITY,
+            WarpedMotionType::Translation => DAV1D_WM_TYPE_TRANSLATION,
+            WarpedMotionType::RotZoom => DAV1D_WM_TYPE_ROT_ZOOM,
+            WarpedMotionType::Affine => DAV1D_WM_TYPE_AFFINE,
+        }
+    }
+}
+
+/// Global motion parameters of a reference frame.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct WarpedMotionParams {
+    /// Type of the transformation.
+    pub type_: WarpedMotionType,
+    /// Affine transformation matrix.
+    pub matrix: [i32; 6],
+}
+
+/// Superresolution parameters of a frame.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SuperRes {
+    /// Whether superresolution is used.
+    pub enabled: bool,
+    /// Denominator of the horizontal downscaling ratio, with a numerator of 8.
+    pub width_scale_denominator: u8,
+}
+
+/// Tiling parameters of a frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tiling {
+    /// Tiles are uniformly spaced.
+    pub uniform: bool,
+    /// Number of bytes used to code tile sizes.
+    pub n_bytes: u8,
+    /// Minimum log2 of the number of tile columns.
+    pub min_log2_cols: u8,
+    /// Maximum log2 of the number of tile columns.
+    pub max_log2_cols: u8,
+    /// Log2 of the number of tile columns.
+    pub log2_cols: u8,
+    /// Number of tile columns.
+    pub cols: u8,
+    /// Minimum log2 of the number of tile rows.
+    pub min_log2_rows: u8,
+    /// Maximum log2 of the number of tile rows.
+    pub max_log2_rows: u8,
+    /// Log2 of the number of tile rows.
+    pub log2_rows: u8,
+    /// Number of tile rows.
+    pub rows: u8,
+    /// Start of each tile column in superblocks, followed by the end of the last one.
+    pub col_start_sb: Vec<u16>,
+    /// Start of each tile row in superblocks, followed by the end of the last one.
+    pub row_start_sb: Vec<u16>,
+    /// Tile used for updating the CDFs.
+    pub update: u16,
+}
+
+/// Quantization parameters of a frame.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Quantization {
+    /// Base quantizer index used for luma AC coefficients.
+    pub yac: u8,
+    /// Luma DC quantizer delta.
+    pub ydc_delta: i8,
+    /// U DC quantizer delta.
+    pub udc_delta: i8,
+    /// U AC quantizer delta.
+    pub uac_delta: i8,
+    /// V DC quantizer delta.
+    pub vdc_delta: i8,
+    /// V AC quantizer delta.
+    pub vac_delta: i8,
+    /// Quantizer matrices are used.
+    pub qm: bool,
+    /// Luma quantizer matrix level.
+    pub qm_y: u8,
+    /// U quantizer matrix level.
+    pub qm_u: u8,
+    /// V quantizer matrix level.
+    pub qm_v: u8,
+}
+
+/// Features of a segment.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SegmentationData {
+    /// Quantizer index delta.
+    pub delta_q: i16,
+    /// Vertical luma loop filter level delta.
+    pub delta_lf_y_v: i8,
+    /// Horizontal luma loop filter level delta.
+    pub delta_lf_y_h: i8,
+    /// U loop filter level delta.
+    pub delta_lf_u: i8,
+    /// V loop filter level delta.
+    pub delta_lf_v: i8,
+    /// Reference frame used by all blocks of the segment, `-1` if none.
+    pub ref_: i8,
+    /// Blocks of the segment are skipped.
+    pub skip: bool,
+    /// Blocks of the segment use global motion.
+    pub globalmv: bool,
+}
+
+/// Segmentation parameters of a frame.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Segmentation {
+    /// Segmentation is enabled.
+    pub enabled: bool,
+    /// The segmentation map is updated.
+    pub update_map: bool,
+    /// The segmentation map is coded relative to the previous one.
+    pub temporal: bool,
+    /// The segment features are updated.
+    pub update_data: bool,
+    /// Features of each segment.
+    pub data: [SegmentationData; DAV1D_MAX_SEGMENTS],
+    /// The segment id is read before the skip flag.
+    pub preskip: bool,
+    /// Highest segment id with enabled features, `-1` if none.
+    pub last_active_segid: i8,
+    /// Segments coded losslessly.
+    pub lossless: [bool; DAV1D_MAX_SEGMENTS],
+    /// Quantizer index of each segment.
+    pub qidx: [u8; DAV1D_MAX_SEGMENTS],
+}
+
+/// Block level quantizer delta parameters of a frame.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DeltaQ {
+    /// Quantizer deltas are present.
+    pub present: bool,
+    /// Log2 of the quantizer delta resolution.
+    pub res_log2: u8,
+}
+
+/// Block level loop filter delta parameters of a frame.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DeltaLf {
+    /// Loop filter deltas are present.
+    pub present: bool,
+    /// Log2 of the loop filter delta resolution.
+    pub res_log2: u8,
+    /// Separate deltas are coded for each filter direction and plane.
+    pub multi: bool,
+}
+
+/// Block level delta parameters of a frame.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Delta {
+    /// Quantizer deltas.
+    pub q: DeltaQ,
+    /// Loop filter deltas.
+    pub lf: DeltaLf,
+}
+
+/// Loop filter parameters of a frame.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct LoopFilter {
+    /// Luma filter levels for vertical and horizontal edges.
+    pub level_y: [u8; 2],
+    /// U filter level.
+    pub level_u: u8,
+    /// V filter level.
+    pub level_v: u8,
+    /// Mode and reference frame based level adjustments are enabled.
+    pub mode_ref_delta_enabled: bool,
+    /// Mode and reference frame based level adjustments are updated.
+    pub mode_ref_delta_update: bool,
+    /// Level adjustments per prediction mode.
+    pub mode_delta: [i8; 2],
+    /// Level adjustments per reference frame.
+    pub ref_delta: [i8; DAV1D_TOTAL_REFS_PER_FRAME],
+    /// Filter sharpness.
+    pub sharpness: u8,
+}
+
+/// CDEF parameters of a frame.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Cdef {
+    /// Damping of the filter minus 3.
+    pub damping: u8,
+    /// Number of bits used to select the strength per block.
+    pub n_bits: u8,
+    /// Luma strengths.
+    pub y_strength: [u8; DAV1D_MAX_CDEF_STRENGTHS],
+    /// Chroma strengths.
+    pub uv_strength: [u8; DAV1D_MAX_CDEF_STRENGTHS],
+}
+
+/// Loop restoration parameters of a frame.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Restoration {
+    /// Filter type of the Y, U and V planes.
+    pub type_: [RestorationType; 3],
+    /// Log2 of the restoration unit size for luma and chroma.
+    pub unit_size: [u8; 2],
+}
+
+/// Film grain synthesis parameters of a frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilmGrainData {
+    /// Random seed of the grain generator.
+    pub seed: u32,
+    /// Piecewise linear scaling function points for luma as `[value, scaling]`.
+    pub y_points: Vec<[u8; 2]>,
+    /// Chroma scaling is derived from the luma scaling function.
+    pub chroma_scaling_from_luma: bool,
+    /// Piecewise linear scaling function points for U and V as `[value, scaling]`.
+    pub uv_points: [Vec<[u8; 2]>; 2],
+    /// Shift applied to the scaling function values.
+    pub scaling_shift: u8,
+    /// Lag of the auto-regressive filter.
+    pub ar_coeff_lag: u8,
+    /// Auto-regressive filter coefficients for luma.
+    pub ar_coeffs_y: [i8; 24],
+    /// Auto-regressive filter coefficients for U and V.
+    pub ar_coeffs_uv: [[i8; 25]; 2],
+    /// Shift applied to the auto-regressive filter coefficients.
+    pub ar_coeff_shift: u8,
+    /// Shift applied to the Gaussian random numbers.
+    pub grain_scale_shift: u8,
+    /// Multipliers of the U and V inputs to the chroma scaling function.
+    pub uv_mult: [i32; 2],
+    /// Multipliers of the average luma input to the chroma scaling function.
+    pub uv_luma_mult: [i32; 2],
+    /// Offsets of the U and V inputs to the chroma scaling function.
+    pub uv_offset: [i32; 2],
+    /// Overlapping grain blocks are blended.
+    pub overlap_flag: bool,
+    /// Samples are clipped to the restricted range after applying the grain.
+    pub clip_to_restricted_range: bool,
+}
+
+impl FilmGrainData {
+    pub(crate) fn from_raw(data: &Dav1dFilmGrainData) -> Self {
+        let uv_points = |i: usize| data.uv_points[i][..data.num_uv_points[i] as usize].to_vec();
+
+        FilmGrainData {
+            seed: data.seed,
+            y_points: data.y_points[..data.num_y_points as usize].to_vec(),
+            chroma_scaling_from_luma: data.chroma_scaling_from_luma != 0,
+            uv_points: [uv_points(0), uv_points(1)],
+            scaling_shift: data.scaling_shift as u8,
+            ar_coeff_lag: data.ar_coeff_lag as u8,
+            ar_coeffs_y: data.ar_coeffs_y,
+            ar_coeffs_uv: data
+                .ar_coeffs_uv
+                .map(|coeffs| coeffs[..25].try_into().unwrap()),
+            ar_coeff_shift: data.ar_coeff_shift as u8,
+            grain_scale_shift: data.grain_scale_shift as u8,
+            uv_mult: data.uv_mult,
+            uv_luma_mult: data.uv_luma_mult,
+            uv_offset: data.uv_offset,
+            overlap_flag: data.overlap_flag != 0,
+            clip_to_restricted_range: data.clip_to_restricted_range != 0,
+        }
+    }
+}
+
+/// An AV1 frame header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameHeader {
+    /// Film grain parameters, if film grain is applied to the frame.
+    pub film_grain: Option<FilmGrainData>,
+    /// Type of the frame.
+    pub frame_type: FrameType,
+    /// Coded width of the frame.
+    pub coded_width: u32,
+    /// Width of the frame after superresolution upscaling.
+    pub upscaled_width: u32,
+    /// Height of the frame.
+    pub height: u32,
+    /// Order hint of the frame.
+    pub frame_offset: u8,
+    /// Temporal layer of the frame.
+    pub temporal_id: u8,
+    /// Spatial layer of the frame.
+    pub spatial_id: u8,
+    /// The frame is a previously decoded frame shown again.
+    pub show_existing_frame: bool,
+    /// Reference slot of the frame shown again.
+    pub existing_frame_idx: u8,
+    /// Frame id, if frame ids are present in the sequence.
+    pub frame_id: u32,
+    /// Frame presentation delay of the decoder model.
+    pub frame_presentation_delay: u32,
+    /// The frame is shown.
+    pub show_frame: bool,
+    /// The frame can be shown later with `show_existing_frame`.
+    pub showable_frame: bool,
+    /// Error resilient mode is enabled.
+    pub error_resilient_mode: bool,
+    /// CDF updates are disabled.
+    pub disable_cdf_update: bool,
+    /// Screen content tools are allowed.
+    pub allow_screen_content_tools: bool,
+    /// Motion vectors are integer.
+    pub force_integer_mv: bool,
+    /// The frame size is coded instead of using the maximum size.
+    pub frame_size_override: bool,
+    /// Reference slot the CDFs and other state are loaded from, `None` if there is none.
+    pub primary_ref_frame: Option<u8>,
+    /// Buffer removal time for each operating point, if present.
+    pub buffer_removal_time: Option<[u32; DAV1D_MAX_OPERATING_POINTS]>,
+    /// Reference slots refreshed with this frame, one bit per slot.
+    pub refresh_frame_flags: u8,
+    /// Render width of the frame.
+    pub render_width: u32,
+    /// Render height of the frame.
+    pub render_height: u32,
+    /// Superresolution parameters.
+    pub super_res: SuperRes,
+    /// The render size differs from the frame size.
+    pub have_render_size: bool,
+    /// Intra block copy is allowed.
+    pub allow_intrabc: bool,
+    /// Reference frames are selected with short signaling.
+    pub frame_ref_short_signaling: bool,
+    /// Reference slot used by each reference frame.
+    pub refidx: [i8; DAV1D_REFS_PER_FRAME],
+    /// High precision motion vectors are allowed.
+    pub hp: bool,
+    /// Interpolation filter.
+    pub subpel_filter_mode: FilterMode,
+    /// The motion mode is selected per block.
+    pub switchable_motion_mode: bool,
+    /// Motion vectors from reference frames are used.
+    pub use_ref_frame_mvs: bool,
+    /// The CDFs are updated at the end of the frame.
+    pub refresh_context: bool,
+    /// Tiling parameters.
+    pub tiling: Tiling,
+    /// Quantization parameters.
+    pub quant: Quantization,
+    /// Segmentation parameters.
+    pub segmentation: Segmentation,
+    /// Block level delta parameters.
+    pub delta: Delta,
+    /// All segments are coded losslessly.
+    pub all_lossless: bool,
+    /// Loop filter parameters.
+    pub loopfilter: LoopFilter,
+    /// CDEF parameters.
+    pub cdef: Cdef,
+    /// Loop restoration parameters.
+    pub restoration: Restoration,
+    /// Transform size selection mode.
+    pub txfm_mode: TxfmMode,
+    /// Compound reference type is selected per block.
+    pub switchable_comp_refs: bool,
+    /// Skip mode is allowed.
+    pub skip_mode_allowed: bool,
+    /// Skip mode is enabled.
+    pub skip_mode_enabled: bool,
+    /// Reference frames used by skip mode.
+    pub skip_mode_refs: [i8; 2],
+    /// Warped motion is allowed.
+    pub warp_motion: bool,
+    /// A reduced set of transform types is used.
+    pub reduced_txtp_set: bool,
+    /// Global motion parameters of each reference frame.
+    pub gmv: [WarpedMotionParams; DAV1D_REFS_PER_FRAME],
+}
+
+impl FrameHeader {
+    pub(crate) fn from_raw(hdr: &Dav1dFrameHeader) -> Self {
+        let tiling = &hdr.tiling;
+        let seg = &hdr.segmentation;
+        let lf = &hdr.loopfilter;
+
+        FrameHeader {
+            film_grain: (hdr.film_grain.present != 0)
+                .then(|| FilmGrainData::from_raw(&hdr.film_grain.data)),
+            frame_type: FrameType::try_from(hdr.frame_type).expect("Invalid Dav1dFrameType"),
+            coded_width: hdr.width[0] as u32,
+            upscaled_width: hdr.width[1] as u32,
+            height: hdr.height as u32,
+            frame_offset: hdr.frame_offset,
+            temporal_id: hdr.temporal_id,
+            spatial_id: hdr.spatial_id,
+            show_existing_frame: hdr.show_existing_frame != 0,
+            existing_frame_idx: hdr.existing_frame_idx,
+            frame_id: hdr.frame_id,
+            frame_presentation_delay: hdr.frame_presentation_delay,
+            show_frame: hdr.show_frame != 0,
+            showable_frame: hdr.showable_frame != 0,
+            error_resilient_mode: hdr.error_resilient_mode != 0,
+            disable_cdf_update: hdr.disable_cdf_update != 0,
+            allow_screen_content_tools: hdr.allow_screen_content_tools != 0,
+            force_integer_mv: hdr.force_integer_mv != 0,
+            frame_size_override: hdr.frame_size_override != 0,
+            primary_ref_frame: (hdr.primary_ref_frame as usize != DAV1D_PRIMARY_REF_NONE)
+                .then_some(hdr.primary_ref_frame),
+            buffer_removal_time: (hdr.buffer_removal_time_present != 0)
+                .then(|| hdr.operating_points.map(|op| op.buffer_removal_time)),
+            refresh_frame_flags: hdr.refresh_frame_flags,
+            render_width: hdr.render_width as u32,
+            render_height: hdr.render_height as u32,
+            super_res: SuperRes {
+                enabled: hdr.super_res.enabled != 0,
+                width_scale_denominator: hdr.super_res.width_scale_denominator,
+            },
+            have_render_size: hdr.have_render_size != 0,
+            allow_intrabc: hdr.allow_intrabc != 0,
+            frame_ref_short_signaling: hdr.frame_ref_short_signaling != 0,
+            refidx: hdr.refidx,
+            hp: hdr.hp != 0,
+            subpel_filter_mode: FilterMode::try_from(hdr.subpel_filter_mode)
+                .expect("Invalid Dav1dFilterMode"),
+            switchable_motion_mode: hdr.switchable_motion_mode != 0,
+            use_ref_frame_mvs: hdr.use_ref_frame_mvs != 0,
+            refresh_context: hdr.refresh_context != 0,
+            tiling: Tiling {
+                uniform: tiling.uniform != 0,
+                n_bytes: tiling.n_bytes,
+                min_log2_cols: tiling.min_log2_cols,
+                max_log2_cols: tiling.max_log2_cols,
+                log2_cols: tiling.log2_cols,
+                cols: tiling.cols,
+                min_log2_rows: tiling.min_log2_rows,
+                max_log2_rows: tiling.max_log2_rows,
+                log2_rows: tiling.log2_rows,
+                rows: tiling.rows,
+                col_start_sb: tiling.col_start_sb[..=tiling.cols as usize].to_vec(),
+                row_start_sb: tiling.row_start_sb[..=tiling.rows as usize].to_vec(),
+                update: tiling.update,
+            },
+            quant: Quantization {
+                yac: hdr.quant.yac,
+                ydc_delta: hdr.quant.ydc_delta,
+                udc_delta: hdr.quant.udc_delta,
+                uac_delta: hdr.quant.uac_delta,
+                vdc_delta: hdr.quant.vdc_delta,
+                vac_delta: hdr.quant.vac_delta,
+                qm: hdr.quant.qm != 0,
+                qm_y: hdr.quant.qm_y,
+                qm_u: hdr.quant.qm_u,
+                qm_v: hdr.quant.qm_v,
+            },
+            segmentation: Segmentation {
+                enabled: seg.enabled != 0,
+                update_map: seg.update_map != 0,
+                temporal: seg.temporal != 0,
+                update_data: seg.update_data != 0,
+                data: seg.seg_data.d.map(|d| SegmentationData {
+                    delta_q: d.delta_q,
+                    delta_lf_y_v: d.delta_lf_y_v,
+                    delta_lf_y_h: d.delta_lf_y_h,
+                    delta_lf_u: d.delta_lf_u,
+                    delta_lf_v: d.delta_lf_v,
+                    ref_: d.ref_,
+                    skip: d.skip != 0,
+                    globalmv: d.globalmv != 0,
+                }),
+                preskip: seg.seg_data.preskip != 0,
+                last_active_segid: seg.seg_data.last_active_segid,
+                lossless: seg.lossless.map(|l| l != 0),
+                qidx: seg.qidx,
+            },
+            delta: Delta {
+                q: DeltaQ {
+                    present: hdr.delta.q.present != 0,
+                    res_log2: hdr.delta.q.res_log2,
+                },
+                lf: DeltaLf {
+                    present: hdr.delta.lf.present != 0,
+                    res_log2: hdr.delta.lf.res_log2,
+                    multi: hdr.delta.lf.multi != 0,
+                },
+            },
+            all_lossless: hdr.all_lossless != 0,
+            loopfilter: LoopFilter {
+                level_y: lf.level_y,
+                level_u: lf.level_u,
+                level_v: lf.level_v,
+                mode_ref_delta_enabled: lf.mode_ref_delta_enabled != 0,
+                mode_ref_delta_update: lf.mode_ref_delta_update != 0,
+                mode_delta: lf.mode_ref_deltas.mode_delta,
+                ref_delta: lf.mode_ref_deltas.ref_delta,
+                sharpness: lf.sharpness,
+            },
+            cdef: Cdef {
+                damping: hdr.cdef.damping,
+                n_bits: hdr.cdef.n_bits,
+                y_strength: hdr.cdef.y_strength,
+                uv_strength: hdr.cdef.uv_strength,
+            },
+            restoration: Restoration {
+                type_: hdr
+                    .restoration
+                    .type_
+                    .map(|t| RestorationType::try_from(t).expect("Invalid Dav1dRestorationType")),
+                unit_size: hdr.restoration.unit_size,
+            },
+            txfm_mode: TxfmMode::try_from(hdr.txfm_mode).expect("Invalid Dav1dTxfmMode"),
+            switchable_comp_refs: hdr.switchable_comp_refs != 0,
+            skip_mode_allowed: hdr.skip_mode_allowed != 0,
+            skip_mode_enabled: hdr.skip_mode_enabled != 0,
+            skip_mode_refs: hdr.skip_mode_refs,
+            warp_motion: hdr.warp_motion != 0,
+            reduced_txtp_set: hdr.reduced_txtp_set != 0,
+            gmv: hdr.gmv.map(|gmv| WarpedMotionParams {
+                type_: WarpedMotionType::try_from(gmv.type_)
+                    .expect("Invalid Dav1dWarpedMotionType"),
+                matrix: gmv.matrix,
+            }),
+        }
+    }
+}
@@ -0,0 +1,399 @@
+//! Reading and writing of IVF files.
+//!
+//! IVF is a simple container that stores each temporal unit of an AV1 stream as a frame with a
+//! 64 bit timestamp.
+
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+const SIGNATURE: &[u8; 4] = b"DKIF";
+const VERSION: u16 = 0;
+const HEADER_LEN: usize = 32;
+const FRAME_HEADER_LEN: usize = 12;
+const FRAME_COUNT_OFFSET: u64 = 24;
+
+/// IVF file header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Header {
+    /// Codec FourCC, `AV01` for AV1.
+    pub fourcc: [u8; 4],
+    /// Width of the frames.
+    pub width: u16,
+    /// Height of the frames.
+    pub height: u16,
+    /// Numerator of the timebase.
+    pub timebase_num: u32,
+    /// Denominator of the timebase.
+    pub timebase_den: u32,
+    /// Number of frames in the file as written by the muxer.
+    ///
+    /// This is not necessarily accurate, e.g. for files that were written without seeking back.
+    pub frame_count: u32,
+}
+
+impl Header {
+    /// Parses an IVF file header.
+    pub fn parse(buf: &[u8; 32]) -> io::Result<Self> {
+        let u16_at = |pos: usize| u16::from_le_bytes([buf[pos], buf[pos + 1]]);
+        let u32_at =
+            |pos: usize| u32::from_le_bytes([buf[pos], buf[pos + 1], buf[pos + 2], buf[pos + 3]]);
+
+        if &buf[0..4] != SIGNATURE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Invalid IVF signature",
+            ));
+        }
+
+        let version = u16_at(4);
+        if version != VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unsupported IVF version {}", version),
+            ));
+        }
+
+        if (u16_at(6) as usize) < HEADER_LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Invalid IVF header length",
+            ));
+        }
+
+        Ok(Header {
+            fourcc: [buf[8], buf[9], buf[10], buf[11]],
+            width: u16_at(12),
+            height: u16_at(14),
+            timebase_den: u32_at(16),
+            timebase_num: u32_at(20),
+            frame_count: u32_at(24),
+        })
+    }
+
+    /// Serializes the header in IVF file format.
+    pub fn to_bytes(&self) -> [u8; 32] {
+        let mut buf = [0u8; HEADER_LEN];
+        buf[0..4].copy_from_slice(SIGNATURE);
+        buf[4..6].copy_from_slice(&VERSION.to_le_bytes());
+        buf[6..8].copy_from_slice(&(HEADER_LEN as u16).to_le_bytes());
+        buf[8..12].copy_from_slice(&self.fourcc);
+        buf[12..14].copy_from_slice(&self.width.to_le_bytes());
+        buf[14..16].copy_from_slice(&self.height.to_le_bytes());
+        buf[16..20].copy_from_slice(&self.timebase_den.to_le_bytes());
+        buf[20..24].copy_from_slice(&self.timebase_num.to_le_bytes());
+        buf[24..28].copy_from_slice(&self.frame_count.to_le_bytes());
+        buf
+    }
+}
+
+/// A frame of an IVF file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Packet {
+    /// Frame data.
+    pub data: Box<[u8]>,
+    /// Presentation timestamp in units of the timebase.
+    pub pts: u64,
+}
+
+/// IVF file reader.
+#[derive(Debug)]
+pub struct Reader<R: Read> {
+    reader: R,
+    header: Header,
+    truncated: bool,
+}
+
+impl<R: Read> Reader<R> {
+    /// Creates a new [`Reader`] and reads the file header from `reader`.
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let mut buf = [0u8; HEADER_LEN];
+        reader.read_exact(&mut buf)?;
+        let header = Header::parse(&buf)?;
+
+        // Skip any header extension
+        let header_len = u16::from_le_bytes([buf[6], buf[7]]) as u64;
+        io::copy(
+            &mut (&mut reader).take(header_len - HEADER_LEN as u64),
+            &mut io::sink(),
+        )?;
+
+        Ok(Reader {
+            reader,
+            header,
+            truncated: false,
+        })
+    }
+
+    /// File header.
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /// Reads the next frame.
+    ///
+    /// Returns `Ok(None)` at the end of the file. A truncated frame at the end of the file also
+    /// ends the file and is reported by [`Reader::is_truncated`].
+    pub fn read_packet(&mut self) -> io::Result<Option<Packet>> {
+        if self.truncated {
+            return Ok(None);
+        }
+
+        let mut buf = [0u8; FRAME_HEADER_LEN];
+        match read_full(&mut self.reader, &mut buf)? {
+            0 => return Ok(None),
+            FRAME_HEADER_LEN => (),
+            _ => {
+                self.truncated = true;
+                return Ok(None);
+            }
+        }
+
+        let len = u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as usize;
+        let pts = u64::from_le_bytes([
+            buf[4], buf[5], buf[6], buf[7], buf[8], buf[9], buf[10], buf[11],
+        ]);
+
+        // Don't trust the length for allocating everything upfront
+        let mut data = Vec::new();
+        let read = (&mut self.reader).take(len as u64).read_to_end(&mut data)?;
+        if read < len {
+            self.truncated = true;
+            return Ok(None);
+        }
+
+        Ok(Some(Packet {
+            data: data.into_boxed_slice(),
+            pts,
+        }))
+    }
+
+    /// Whether the file ended with a truncated frame.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: Read> Iterator for Reader<R> {
+    type Item = io::Result<Packet>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_packet().transpose()
+    }
+}
+
+/// IVF file writer.
+#[derive(Debug)]
+pub struct Writer<W: Write> {
+    writer: W,
+    frame_count: u32,
+    /// Number of bytes written including the file header.
+    len: u64,
+}
+
+impl<W: Write> Writer<W> {
+    /// Creates a new [`Writer`] and writes the file header to `writer`.
+    ///
+    /// The frame count of `header` is written as is and only updated by [`Writer::finish`].
+    pub fn new(mut writer: W, header: &Header) -> io::Result<Self> {
+        writer.write_all(&header.to_bytes())?;
+
+        Ok(Writer {
+            writer,
+            frame_count: 0,
+            len: HEADER_LEN as u64,
+        })
+    }
+
+    /// Writes a frame with the given presentation timestamp in units of the timebase.
+    pub fn write_packet(&mut self, data: &[u8], pts: u64) -> io::Result<()> {
+        let len = u32::try_from(data.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "IVF frame too large"))?;
+
+        let mut buf = [0u8; FRAME_HEADER_LEN];
+        buf[0..4].copy_from_slice(&len.to_le_bytes());
+        buf[4..12].copy_from_slice(&pts.to_le_bytes());
+        self.writer.write_all(&buf)?;
+        self.writer.write_all(data)?;
+
+        self.frame_count = self.frame_count.wrapping_add(1);
+        self.len += (FRAME_HEADER_LEN + data.len()) as u64;
+
+        Ok(())
+    }
+
+    /// Number of frames written so far.
+    pub fn frame_count(&self) -> u32 {
+        self.frame_count
+    }
+
+    /// Flushes and returns the underlying writer without updating the frame count of the header.
+    pub fn into_inner(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+impl<W: Write + Seek> Writer<W> {
+    /// Updates the frame count of the header with the number of frames written, flushes and
+    /// returns the underlying writer.
+    ///
+    /// The header doesn't have to be at the start of the stream, e.g. when appending to an existing
+    /// file. The writer is positioned at the end of the written data afterwards.
+    pub fn finish(mut self) -> io::Result<W> {
+        let end = self.writer.stream_position()?;
+        // The underlying writer is only written to by us, so the header is `len` bytes back
+        let start = end.checked_sub(self.len).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Stream position before the IVF header",
+            )
+        })?;
+        self.writer
+            .seek(SeekFrom::Start(start + FRAME_COUNT_OFFSET))?;
+        self.writer.write_all(&self.frame_count.to_le_bytes())?;
+        self.writer.seek(SeekFrom::Start(end))?;
+        self.writer.flush()?;
+
+        Ok(self.writer)
+    }
+}
+
+fn read_full(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut pos = 0;
+    while pos < buf.len() {
+        match reader.read(&mut buf[pos..]) {
+            Ok(0) => break,
+            Ok(n) => pos += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(pos)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    static TEST_FILE_420_8: &[u8] = include_bytes!("../test-420-8.ivf");
+
+    #[test]
+    fn test_read() {
+        let mut r = Reader::new(TEST_FILE_420_8).unwrap();
+        assert_eq!(
+            r.header(),
+            &Header {
+                fourcc: *b"AV01",
+                width: 320,
+                height: 240,
+                timebase_num: 1,
+                timebase_den: 1000,
+                frame_count: 5,
+            }
+        );
+
+        let packets = (&mut r).collect::<io::Result<Vec<_>>>().unwrap();
+        assert_eq!(
+            packets.iter().map(|p| p.pts).collect::<Vec<_>>(),
+            [0, 33, 67, 100, 133]
+        );
+        assert_eq!(
+            packets.iter().map(|p| p.data.len()).collect::<Vec<_>>(),
+            [64, 89, 5, 29, 5]
+        );
+        assert!(!r.is_truncated());
+    }
+
+    #[test]
+    fn test_truncated() {
+        // Truncated in the middle of the last frame
+        let file = &TEST_FILE_420_8[..TEST_FILE_420_8.len() - 2];
+        let mut r = Reader::new(file).unwrap();
+        assert_eq!(r.by_ref().count(), 4);
+        assert!(r.is_truncated());
+
+        // Truncated in the middle of the last frame header
+        let file = &TEST_FILE_420_8[..TEST_FILE_420_8.len() - 5 - 4];
+        let mut r = Reader::new(file).unwrap();
+        assert_eq!(r.by_ref().count(), 4);
+        assert!(r.is_truncated());
+    }
+
+    #[test]
+    fn test_invalid_header() {
+        let mut file = TEST_FILE_420_8.to_vec();
+        file[0] = b'X';
+        let err = Reader::new(&file[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let mut file = TEST_FILE_420_8.to_vec();
+        file[4] = 1;
+        let err = Reader::new(&file[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let err = Reader::new(&TEST_FILE_420_8[..16]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_write() {
+        let r = Reader::new(TEST_FILE_420_8).unwrap();
+        let header = Header {
+            frame_count: 0,
+            ..*r.header()
+        };
+
+        let mut w = Writer::new(io::Cursor::new(Vec::new()), &header).unwrap();
+        for packet in r {
+            let packet = packet.unwrap();
+            w.write_packet(&packet.data, packet.pts).unwrap();
+        }
+        assert_eq!(w.frame_count(), 5);
+
+        let file = w.finish().unwrap().into_inner();
+        assert_eq!(file, TEST_FILE_420_8);
+    }
+
+    #[test]
+    fn test_write_appended() {
+        let r = Reader::new(TEST_FILE_420_8).unwrap();
+        let header = Header {
+            frame_count: 0,
+            ..*r.header()
+        };
+
+        let mut cursor = io::Cursor::new(Vec::new());
+        cursor.write_all(b"prefix").unwrap();
+        let mut w = Writer::new(cursor, &header).unwrap();
+        for packet in r {
+            let packet = packet.unwrap();
+            w.write_packet(&packet.data, packet.pts).unwrap();
+        }
+
+        let cursor = w.finish().unwrap();
+        assert_eq!(cursor.position(), (6 + TEST_FILE_420_8.len()) as u64);
+        let file = cursor.into_inner();
+        assert_eq!(&file[..6], b"prefix");
+        assert_eq!(&file[6..], TEST_FILE_420_8);
+    }
+
+    #[test]
+    fn test_write_unseekable() {
+        let mut r = Reader::new(TEST_FILE_420_8).unwrap();
+        let header = *r.header();
+        let packet = r.read_packet().unwrap().unwrap();
+
+        let mut w = Writer::new(Vec::new(), &header).unwrap();
+        w.write_packet(&packet.data, packet.pts).unwrap();
+        let file = w.into_inner().unwrap();
+
+        let mut r = Reader::new(&file[..]).unwrap();
+        assert_eq!(r.header(), &header);
+        assert_eq!(r.read_packet().unwrap(), Some(packet));
+        assert_eq!(r.read_packet().unwrap(), None);
+    }
+}
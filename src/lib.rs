@@ -1,13 +1,38 @@
 use dav1d_sys::*;
 
 pub use av_data::pixel;
+use std::any::Any;
 use std::ffi::{c_int, c_void};
 use std::fmt::{self, Debug};
-use std::i64;
 use std::mem;
 use std::ptr;
 use std::sync::Arc;
 
+use logger::Logger;
+
+pub mod annexb;
+pub mod av1c;
+pub mod avif;
+mod bmff;
+pub mod export;
+pub mod headers;
+pub mod ivf;
+mod logger;
+#[cfg(feature = "matroska")]
+pub mod matroska;
+#[cfg(feature = "mp4")]
+pub mod mp4;
+pub mod obu;
+mod rgb;
+pub mod section5;
+mod semi_planar;
+pub mod y4m;
+pub mod yuv;
+
+pub use headers::{FilmGrainData, FrameHeader, SequenceHeader};
+pub use rgb::RgbFormat;
+pub use semi_planar::SemiPlanarFormat;
+
 /// Error enum return by various `dav1d` operations.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[non_exhaustive]
@@ -71,6 +96,121 @@ impl fmt::Display for Error {
 
 impl std::error::Error for Error {}
 
+fn bits_per_component(hbd: u8) -> Option<BitsPerComponent> {
+    match hbd {
+        0 => Some(BitsPerComponent(8)),
+        1 => Some(BitsPerComponent(10)),
+        2 => Some(BitsPerComponent(12)),
+        _ => None,
+    }
+}
+
+fn pixel_layout(layout: Dav1dPixelLayout) -> PixelLayout {
+    #[allow(non_upper_case_globals)]
+    match layout {
+        DAV1D_PIXEL_LAYOUT_I400 => PixelLayout::I400,
+        DAV1D_PIXEL_LAYOUT_I420 => PixelLayout::I420,
+        DAV1D_PIXEL_LAYOUT_I422 => PixelLayout::I422,
+        DAV1D_PIXEL_LAYOUT_I444 => PixelLayout::I444,
+        _ => unreachable!(),
+    }
+}
+
+fn color_primaries(pri: Dav1dColorPrimaries) -> pixel::ColorPrimaries {
+    #[allow(non_upper_case_globals)]
+    match pri {
+        DAV1D_COLOR_PRI_BT709 => pixel::ColorPrimaries::BT709,
+        DAV1D_COLOR_PRI_UNKNOWN => pixel::ColorPrimaries::Unspecified,
+        DAV1D_COLOR_PRI_BT470M => pixel::ColorPrimaries::BT470M,
+        DAV1D_COLOR_PRI_BT470BG => pixel::ColorPrimaries::BT470BG,
+        DAV1D_COLOR_PRI_BT601 => pixel::ColorPrimaries::BT470BG,
+        DAV1D_COLOR_PRI_SMPTE240 => pixel::ColorPrimaries::ST240M,
+        DAV1D_COLOR_PRI_FILM => pixel::ColorPrimaries::Film,
+        DAV1D_COLOR_PRI_BT2020 => pixel::ColorPrimaries::BT2020,
+        DAV1D_COLOR_PRI_XYZ => pixel::ColorPrimaries::ST428,
+        DAV1D_COLOR_PRI_SMPTE431 => pixel::ColorPrimaries::P3DCI,
+        DAV1D_COLOR_PRI_SMPTE432 => pixel::ColorPrimaries::P3Display,
+        DAV1D_COLOR_PRI_EBU3213 => pixel::ColorPrimaries::Tech3213,
+        // Reserved values are not rejected by dav1d
+        _ => pixel::ColorPrimaries::Unspecified,
+    }
+}
+
+fn transfer_characteristic(trc: Dav1dTransferCharacteristics) -> pixel::TransferCharacteristic {
+    #[allow(non_upper_case_globals)]
+    match trc {
+        DAV1D_TRC_BT709 => pixel::TransferCharacteristic::BT1886,
+        DAV1D_TRC_UNKNOWN => pixel::TransferCharacteristic::Unspecified,
+        DAV1D_TRC_BT470M => pixel::TransferCharacteristic::BT470M,
+        DAV1D_TRC_BT470BG => pixel::TransferCharacteristic::BT470BG,
+        DAV1D_TRC_BT601 => pixel::TransferCharacteristic::ST170M,
+        DAV1D_TRC_SMPTE240 => pixel::TransferCharacteristic::ST240M,
+        DAV1D_TRC_LINEAR => pixel::TransferCharacteristic::Linear,
+        DAV1D_TRC_LOG100 => pixel::TransferCharacteristic::Logarithmic100,
+        DAV1D_TRC_LOG100_SQRT10 => pixel::TransferCharacteristic::Logarithmic316,
+        DAV1D_TRC_IEC61966 => pixel::TransferCharacteristic::SRGB,
+        DAV1D_TRC_BT1361 => pixel::TransferCharacteristic::BT1886,
+        DAV1D_TRC_SRGB => pixel::TransferCharacteristic::SRGB,
+        DAV1D_TRC_BT2020_10BIT => pixel::TransferCharacteristic::BT2020Ten,
+        DAV1D_TRC_BT2020_12BIT => pixel::TransferCharacteristic::BT2020Twelve,
+        DAV1D_TRC_SMPTE2084 => pixel::TransferCharacteristic::PerceptualQuantizer,
+        DAV1D_TRC_SMPTE428 => pixel::TransferCharacteristic::ST428,
+        DAV1D_TRC_HLG => pixel::TransferCharacteristic::HybridLogGamma,
+        // Reserved values are not rejected by dav1d
+        _ => pixel::TransferCharacteristic::Unspecified,
+    }
+}
+
+fn matrix_coefficients(mtrx: Dav1dMatrixCoefficients) -> pixel::MatrixCoefficients {
+    #[allow(non_upper_case_globals)]
+    match mtrx {
+        DAV1D_MC_IDENTITY => pixel::MatrixCoefficients::Identity,
+        DAV1D_MC_BT709 => pixel::MatrixCoefficients::BT709,
+        DAV1D_MC_UNKNOWN => pixel::MatrixCoefficients::Unspecified,
+        DAV1D_MC_FCC => pixel::MatrixCoefficients::BT470M,
+        DAV1D_MC_BT470BG => pixel::MatrixCoefficients::BT470BG,
+        DAV1D_MC_BT601 => pixel::MatrixCoefficients::BT470BG,
+        DAV1D_MC_SMPTE240 => pixel::MatrixCoefficients::ST240M,
+        DAV1D_MC_SMPTE_YCGCO => pixel::MatrixCoefficients::YCgCo,
+        DAV1D_MC_BT2020_NCL => pixel::MatrixCoefficients::BT2020NonConstantLuminance,
+        DAV1D_MC_BT2020_CL => pixel::MatrixCoefficients::BT2020ConstantLuminance,
+        DAV1D_MC_SMPTE2085 => pixel::MatrixCoefficients::ST2085,
+        DAV1D_MC_CHROMAT_NCL => pixel::MatrixCoefficients::ChromaticityDerivedNonConstantLuminance,
+        DAV1D_MC_CHROMAT_CL => pixel::MatrixCoefficients::ChromaticityDerivedConstantLuminance,
+        DAV1D_MC_ICTCP => pixel::MatrixCoefficients::ICtCp,
+        // Reserved values are not rejected by dav1d
+        _ => pixel::MatrixCoefficients::Unspecified,
+    }
+}
+
+fn color_range(color_range: u8) -> pixel::YUVRange {
+    match color_range {
+        0 => pixel::YUVRange::Limited,
+        _ => pixel::YUVRange::Full,
+    }
+}
+
+fn chroma_location(chr: Dav1dChromaSamplePosition) -> pixel::ChromaLocation {
+    // According to y4m mapping declared in dav1d's output/y4m2.c and applied from FFmpeg's yuv4mpegdec.c
+    match chr {
+        DAV1D_CHR_UNKNOWN | DAV1D_CHR_COLOCATED => pixel::ChromaLocation::Center,
+        DAV1D_CHR_VERTICAL => pixel::ChromaLocation::Left,
+        // Reserved value, not rejected by dav1d
+        _ => pixel::ChromaLocation::Unspecified,
+    }
+}
+
+// Unlike `chroma_location()` this keeps co-located chroma apart from unknown chroma positions
+fn chroma_sample_position(chr: Dav1dChromaSamplePosition) -> pixel::ChromaLocation {
+    match chr {
+        DAV1D_CHR_UNKNOWN => pixel::ChromaLocation::Unspecified,
+        DAV1D_CHR_VERTICAL => pixel::ChromaLocation::Left,
+        DAV1D_CHR_COLOCATED => pixel::ChromaLocation::TopLeft,
+        // Reserved value, not rejected by dav1d
+        _ => pixel::ChromaLocation::Unspecified,
+    }
+}
+
 /// Picture parameters used for allocation.
 #[derive(Debug)]
 pub struct PictureParameters {
@@ -91,14 +231,7 @@ impl PictureParameters {
     ///
     /// Check [`PictureParameters::bit_depth`] for the number of storage bits.
     pub fn bits_per_component(&self) -> Option<BitsPerComponent> {
-        unsafe {
-            match (*self.pic.as_ref().seq_hdr).hbd {
-                0 => Some(BitsPerComponent(8)),
-                1 => Some(BitsPerComponent(10)),
-                2 => Some(BitsPerComponent(12)),
-                _ => None,
-            }
-        }
+        unsafe { bits_per_component((*self.pic.as_ref().seq_hdr).hbd) }
     }
 
     /// Width of the frame.
@@ -113,119 +246,33 @@ impl PictureParameters {
 
     /// Pixel layout of the frame.
     pub fn pixel_layout(&self) -> PixelLayout {
-        unsafe {
-            #[allow(non_upper_case_globals)]
-            match self.pic.as_ref().p.layout {
-                DAV1D_PIXEL_LAYOUT_I400 => PixelLayout::I400,
-                DAV1D_PIXEL_LAYOUT_I420 => PixelLayout::I420,
-                DAV1D_PIXEL_LAYOUT_I422 => PixelLayout::I422,
-                DAV1D_PIXEL_LAYOUT_I444 => PixelLayout::I444,
-                _ => unreachable!(),
-            }
-        }
+        unsafe { pixel_layout(self.pic.as_ref().p.layout) }
     }
 
     /// Chromaticity coordinates of the source colour primaries.
     pub fn color_primaries(&self) -> pixel::ColorPrimaries {
-        unsafe {
-            #[allow(non_upper_case_globals)]
-            match (*self.pic.as_ref().seq_hdr).pri {
-                DAV1D_COLOR_PRI_BT709 => pixel::ColorPrimaries::BT709,
-                DAV1D_COLOR_PRI_UNKNOWN => pixel::ColorPrimaries::Unspecified,
-                DAV1D_COLOR_PRI_BT470M => pixel::ColorPrimaries::BT470M,
-                DAV1D_COLOR_PRI_BT470BG => pixel::ColorPrimaries::BT470BG,
-                DAV1D_COLOR_PRI_BT601 => pixel::ColorPrimaries::BT470BG,
-                DAV1D_COLOR_PRI_SMPTE240 => pixel::ColorPrimaries::ST240M,
-                DAV1D_COLOR_PRI_FILM => pixel::ColorPrimaries::Film,
-                DAV1D_COLOR_PRI_BT2020 => pixel::ColorPrimaries::BT2020,
-                DAV1D_COLOR_PRI_XYZ => pixel::ColorPrimaries::ST428,
-                DAV1D_COLOR_PRI_SMPTE431 => pixel::ColorPrimaries::P3DCI,
-                DAV1D_COLOR_PRI_SMPTE432 => pixel::ColorPrimaries::P3Display,
-                DAV1D_COLOR_PRI_EBU3213 => pixel::ColorPrimaries::Tech3213,
-                23..=DAV1D_COLOR_PRI_RESERVED => pixel::ColorPrimaries::Unspecified,
-                _ => unreachable!(),
-            }
-        }
+        unsafe { color_primaries((*self.pic.as_ref().seq_hdr).pri) }
     }
 
     /// Transfer characteristics function.
     pub fn transfer_characteristic(&self) -> pixel::TransferCharacteristic {
-        unsafe {
-            #[allow(non_upper_case_globals)]
-            match (*self.pic.as_ref().seq_hdr).trc {
-                DAV1D_TRC_BT709 => pixel::TransferCharacteristic::BT1886,
-                DAV1D_TRC_UNKNOWN => pixel::TransferCharacteristic::Unspecified,
-                DAV1D_TRC_BT470M => pixel::TransferCharacteristic::BT470M,
-                DAV1D_TRC_BT470BG => pixel::TransferCharacteristic::BT470BG,
-                DAV1D_TRC_BT601 => pixel::TransferCharacteristic::ST170M,
-                DAV1D_TRC_SMPTE240 => pixel::TransferCharacteristic::ST240M,
-                DAV1D_TRC_LINEAR => pixel::TransferCharacteristic::Linear,
-                DAV1D_TRC_LOG100 => pixel::TransferCharacteristic::Logarithmic100,
-                DAV1D_TRC_LOG100_SQRT10 => pixel::TransferCharacteristic::Logarithmic316,
-                DAV1D_TRC_IEC61966 => pixel::TransferCharacteristic::SRGB,
-                DAV1D_TRC_BT1361 => pixel::TransferCharacteristic::BT1886,
-                DAV1D_TRC_SRGB => pixel::TransferCharacteristic::SRGB,
-                DAV1D_TRC_BT2020_10BIT => pixel::TransferCharacteristic::BT2020Ten,
-                DAV1D_TRC_BT2020_12BIT => pixel::TransferCharacteristic::BT2020Twelve,
-                DAV1D_TRC_SMPTE2084 => pixel::TransferCharacteristic::PerceptualQuantizer,
-                DAV1D_TRC_SMPTE428 => pixel::TransferCharacteristic::ST428,
-                DAV1D_TRC_HLG => pixel::TransferCharacteristic::HybridLogGamma,
-                19..=DAV1D_TRC_RESERVED => pixel::TransferCharacteristic::Unspecified,
-                _ => unreachable!(),
-            }
-        }
+        unsafe { transfer_characteristic((*self.pic.as_ref().seq_hdr).trc) }
     }
 
     /// Matrix coefficients used in deriving luma and chroma signals from the
     /// green, blue and red or X, Y and Z primaries.
     pub fn matrix_coefficients(&self) -> pixel::MatrixCoefficients {
-        unsafe {
-            #[allow(non_upper_case_globals)]
-            match (*self.pic.as_ref().seq_hdr).mtrx {
-                DAV1D_MC_IDENTITY => pixel::MatrixCoefficients::Identity,
-                DAV1D_MC_BT709 => pixel::MatrixCoefficients::BT709,
-                DAV1D_MC_UNKNOWN => pixel::MatrixCoefficients::Unspecified,
-                DAV1D_MC_FCC => pixel::MatrixCoefficients::BT470M,
-                DAV1D_MC_BT470BG => pixel::MatrixCoefficients::BT470BG,
-                DAV1D_MC_BT601 => pixel::MatrixCoefficients::BT470BG,
-                DAV1D_MC_SMPTE240 => pixel::MatrixCoefficients::ST240M,
-                DAV1D_MC_SMPTE_YCGCO => pixel::MatrixCoefficients::YCgCo,
-                DAV1D_MC_BT2020_NCL => pixel::MatrixCoefficients::BT2020NonConstantLuminance,
-                DAV1D_MC_BT2020_CL => pixel::MatrixCoefficients::BT2020ConstantLuminance,
-                DAV1D_MC_SMPTE2085 => pixel::MatrixCoefficients::ST2085,
-                DAV1D_MC_CHROMAT_NCL => {
-                    pixel::MatrixCoefficients::ChromaticityDerivedNonConstantLuminance
-                }
-                DAV1D_MC_CHROMAT_CL => {
-                    pixel::MatrixCoefficients::ChromaticityDerivedConstantLuminance
-                }
-                DAV1D_MC_ICTCP => pixel::MatrixCoefficients::ICtCp,
-                15..=DAV1D_MC_RESERVED => pixel::MatrixCoefficients::Unspecified,
-                _ => unreachable!(),
-            }
-        }
+        unsafe { matrix_coefficients((*self.pic.as_ref().seq_hdr).mtrx) }
     }
 
     /// YUV color range.
     pub fn color_range(&self) -> pixel::YUVRange {
-        unsafe {
-            match (*self.pic.as_ref().seq_hdr).color_range {
-                0 => pixel::YUVRange::Limited,
-                _ => pixel::YUVRange::Full,
-            }
-        }
+        unsafe { color_range((*self.pic.as_ref().seq_hdr).color_range) }
     }
 
     /// Sample position for subsampled chroma.
     pub fn chroma_location(&self) -> pixel::ChromaLocation {
-        // According to y4m mapping declared in dav1d's output/y4m2.c and applied from FFmpeg's yuv4mpegdec.c
-        unsafe {
-            match (*self.pic.as_ref().seq_hdr).chr {
-                DAV1D_CHR_UNKNOWN | DAV1D_CHR_COLOCATED => pixel::ChromaLocation::Center,
-                DAV1D_CHR_VERTICAL => pixel::ChromaLocation::Left,
-                _ => unreachable!(),
-            }
-        }
+        unsafe { chroma_location((*self.pic.as_ref().seq_hdr).chr) }
     }
 }
 
@@ -328,6 +375,7 @@ unsafe impl PictureAllocator for DefaultAllocator {
 #[derive(Debug)]
 pub struct Settings {
     dav1d_settings: Dav1dSettings,
+    logger: Option<Arc<Logger>>,
 }
 
 unsafe impl Send for Settings {}
@@ -349,6 +397,7 @@ impl Settings {
 
             Self {
                 dav1d_settings: dav1d_settings.assume_init(),
+                logger: None,
             }
         }
     }
@@ -425,6 +474,36 @@ impl Settings {
         InloopFilterType::from_bits_truncate(self.dav1d_settings.inloop_filters)
     }
 
+    /// Sets a callback that receives all messages logged by `dav1d`.
+    ///
+    /// By default `dav1d` writes its messages to `stderr`.
+    pub fn set_logger<F: Fn(&str) + Send + Sync + 'static>(&mut self, logger: F) {
+        let logger = Arc::new(Logger(Box::new(logger)));
+
+        self.dav1d_settings.logger = Dav1dLogger {
+            cookie: Arc::as_ptr(&logger) as *mut c_void,
+            callback: logger::logger_callback as *mut c_void,
+        };
+        self.logger = Some(logger);
+    }
+
+    /// Forwards all messages logged by `dav1d` to the [`log`](https://docs.rs/log) crate.
+    ///
+    /// The messages are logged as warnings with the `dav1d` target.
+    #[cfg(feature = "log")]
+    pub fn set_log_crate_logger(&mut self) {
+        self.set_logger(|msg| log::warn!(target: "dav1d", "{}", msg));
+    }
+
+    /// Disables all logging by `dav1d`.
+    pub fn disable_logger(&mut self) {
+        self.dav1d_settings.logger = Dav1dLogger {
+            cookie: ptr::null_mut(),
+            callback: ptr::null_mut(),
+        };
+        self.logger = None;
+    }
+
     pub fn set_decode_frame_type(&mut self, decode_frame_type: DecodeFrameType) {
         self.dav1d_settings.decode_frame_type = decode_frame_type.into();
     }
@@ -444,6 +523,16 @@ bitflags::bitflags! {
     }
 }
 
+bitflags::bitflags! {
+    #[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+    pub struct EventFlags: u32 {
+        /// A new sequence header was found since the last picture was returned.
+        const NEW_SEQUENCE = DAV1D_EVENT_FLAG_NEW_SEQUENCE;
+        /// New operating parameters information was found since the last picture was returned.
+        const NEW_OP_PARAMS_INFO = DAV1D_EVENT_FLAG_NEW_OP_PARAMS_INFO;
+    }
+}
+
 #[derive(Default, Debug, Copy, Clone, PartialEq, Eq)]
 pub enum DecodeFrameType {
     #[default]
@@ -496,12 +585,77 @@ impl From<std::convert::Infallible> for TryFromEnumError {
 
 impl std::error::Error for TryFromEnumError {}
 
+/// Version of the linked `dav1d` library.
+pub fn version() -> &'static str {
+    unsafe {
+        std::ffi::CStr::from_ptr(dav1d_version())
+            .to_str()
+            .expect("Invalid dav1d version string")
+    }
+}
+
+/// API version of the `dav1d` library.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ApiVersion {
+    pub major: u8,
+    pub minor: u8,
+    pub patch: u8,
+}
+
+impl ApiVersion {
+    /// Creates a new [`ApiVersion`] instance, e.g. for comparing against [`api_version`].
+    pub const fn new(major: u8, minor: u8, patch: u8) -> Self {
+        ApiVersion {
+            major,
+            minor,
+            patch,
+        }
+    }
+}
+
+impl fmt::Display for ApiVersion {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// API version of the linked `dav1d` library.
+pub fn api_version() -> ApiVersion {
+    let version = unsafe { dav1d_version_api() };
+
+    ApiVersion {
+        major: (version >> 16) as u8,
+        minor: (version >> 8) as u8,
+        patch: version as u8,
+    }
+}
+
+/// Parses the first sequence header found in `buf`.
+///
+/// `buf` can contain any number of OBUs, e.g. the `configOBUs` of an `av1C` record or the first
+/// temporal unit of a stream. This allows to probe the stream parameters without creating a
+/// [`Decoder`].
+pub fn parse_sequence_header(buf: &[u8]) -> Result<SequenceHeader, Error> {
+    unsafe {
+        let mut seq_hdr = mem::MaybeUninit::<Dav1dSequenceHeader>::zeroed();
+
+        let ret = dav1d_parse_sequence_header(seq_hdr.as_mut_ptr(), buf.as_ptr(), buf.len());
+        if ret < 0 {
+            return Err(Error::from(ret));
+        }
+
+        Ok(SequenceHeader::from_raw(&seq_hdr.assume_init()))
+    }
+}
+
 /// A `dav1d` decoder instance.
 #[derive(Debug)]
 pub struct Decoder<A: PictureAllocator = DefaultAllocator> {
     dec: ptr::NonNull<Dav1dContext>,
     pending_data: Option<Dav1dData>,
     allocator: Option<Arc<A>>,
+    // Keeps the logger cookie alive as long as `dav1d` can call it.
+    _logger: Option<Arc<Logger>>,
 }
 
 static_assertions::assert_impl_all!(Decoder<DefaultAllocator>: Send, Sync, Debug);
@@ -511,6 +665,21 @@ unsafe extern "C" fn release_wrapped_data<T: AsRef<[u8]>>(_data: *const u8, cook
     drop(buf);
 }
 
+type UserData = Box<dyn Any + Send + Sync>;
+
+unsafe extern "C" fn release_user_data(_user_data: *const u8, cookie: *mut c_void) {
+    let user_data = Box::from_raw(cookie as *mut UserData);
+    drop(user_data);
+}
+
+unsafe fn user_data<'a, T: Any>(user_data: &Dav1dUserData) -> Option<&'a T> {
+    if user_data.data.is_null() {
+        None
+    } else {
+        (*(user_data.data as *const UserData)).downcast_ref()
+    }
+}
+
 impl Decoder {
     /// Creates a new [`Decoder`] instance with given [`Settings`].
     pub fn with_settings(settings: &Settings) -> Result<Self, Error> {
@@ -527,6 +696,7 @@ impl Decoder {
                 dec: ptr::NonNull::new(dec.assume_init()).unwrap(),
                 pending_data: None,
                 allocator: None,
+                _logger: settings.logger.clone(),
             })
         }
     }
@@ -599,7 +769,7 @@ impl<A: PictureAllocator> Decoder<A> {
 
             let mut dec = mem::MaybeUninit::uninit();
 
-            let settings = Dav1dSettings {
+            let dav1d_settings = Dav1dSettings {
                 allocator: Dav1dPicAllocator {
                     cookie: &*allocator as *const A as *mut c_void,
                     alloc_picture_callback: Some(alloc_picture_callback::<A>),
@@ -607,7 +777,7 @@ impl<A: PictureAllocator> Decoder<A> {
                 },
                 ..settings.dav1d_settings
             };
-            let ret = dav1d_open(dec.as_mut_ptr(), &settings);
+            let ret = dav1d_open(dec.as_mut_ptr(), &dav1d_settings);
 
             if ret < 0 {
                 return Err(Error::from(ret));
@@ -617,6 +787,7 @@ impl<A: PictureAllocator> Decoder<A> {
                 dec: ptr::NonNull::new(dec.assume_init()).unwrap(),
                 pending_data: None,
                 allocator: Some(allocator),
+                _logger: settings.logger.clone(),
             })
         }
     }
@@ -658,6 +829,36 @@ impl<A: PictureAllocator> Decoder<A> {
         offset: Option<i64>,
         timestamp: Option<i64>,
         duration: Option<i64>,
+    ) -> Result<(), Error> {
+        self.send_data_inner(buf, offset, timestamp, duration, None)
+    }
+
+    /// Send new AV1 data to the decoder together with arbitrary user data.
+    ///
+    /// The user data can be retrieved from the resulting pictures via [`Picture::user_data`].
+    ///
+    /// Otherwise this behaves exactly like [`Decoder::send_data`].
+    pub fn send_data_with_user_data<
+        T: AsRef<[u8]> + Send + 'static,
+        U: Any + Send + Sync + 'static,
+    >(
+        &mut self,
+        buf: T,
+        offset: Option<i64>,
+        timestamp: Option<i64>,
+        duration: Option<i64>,
+        user_data: U,
+    ) -> Result<(), Error> {
+        self.send_data_inner(buf, offset, timestamp, duration, Some(Box::new(user_data)))
+    }
+
+    fn send_data_inner<T: AsRef<[u8]> + Send + 'static>(
+        &mut self,
+        buf: T,
+        offset: Option<i64>,
+        timestamp: Option<i64>,
+        duration: Option<i64>,
+        user_data: Option<UserData>,
     ) -> Result<(), Error> {
         assert!(
             self.pending_data.is_none(),
@@ -686,6 +887,20 @@ impl<A: PictureAllocator> Decoder<A> {
             if let Some(duration) = duration {
                 data.m.duration = duration;
             }
+            if let Some(user_data) = user_data {
+                let user_data = Box::into_raw(Box::new(user_data));
+                let ret = dav1d_data_wrap_user_data(
+                    &mut data,
+                    user_data as *const u8,
+                    Some(release_user_data),
+                    user_data as *mut c_void,
+                );
+                if ret < 0 {
+                    drop(Box::from_raw(user_data));
+                    dav1d_data_unref(&mut data);
+                    return Err(Error::from(ret));
+                }
+            }
 
             let ret = dav1d_send_data(self.dec.as_ptr(), &mut data);
             if ret < 0 {
@@ -783,6 +998,60 @@ impl<A: PictureAllocator> Decoder<A> {
             }
         }
     }
+
+    /// Apply film grain to a picture.
+    ///
+    /// This is useful if the decoder was created with [`Settings::set_apply_grain`] disabled. The
+    /// returned picture has the film grain of `picture` applied, or is a new reference to it if it
+    /// has no film grain parameters.
+    ///
+    /// `picture` must have been returned by this decoder.
+    pub fn apply_grain(&mut self, picture: &Picture<A>) -> Result<Picture<A>, Error> {
+        unsafe {
+            let mut pic: Dav1dPicture = mem::zeroed();
+            let ret = dav1d_apply_grain(self.dec.as_ptr(), &mut pic, &picture.inner.pic);
+
+            if ret < 0 {
+                Err(Error::from(ret))
+            } else {
+                let inner = InnerPicture { pic };
+                Ok(Picture {
+                    inner: Arc::new(inner),
+                    allocator: self.allocator.clone(),
+                })
+            }
+        }
+    }
+
+    /// Get the events that occurred since the last call.
+    ///
+    /// The flags are reset by this call.
+    pub fn event_flags(&mut self) -> Result<EventFlags, Error> {
+        unsafe {
+            let mut flags: Dav1dEventFlags = 0;
+            let ret = dav1d_get_event_flags(self.dec.as_ptr(), &mut flags);
+            if ret < 0 {
+                Err(Error::from(ret))
+            } else {
+                Ok(EventFlags::from_bits_truncate(flags))
+            }
+        }
+    }
+
+    /// Get the properties of the input data that caused the last decoding error.
+    ///
+    /// The properties are reset by this call.
+    pub fn decode_error_data_props(&mut self) -> Result<DataProps, Error> {
+        unsafe {
+            let mut props: Dav1dDataProps = mem::zeroed();
+            let ret = dav1d_get_decode_error_data_props(self.dec.as_ptr(), &mut props);
+            if ret < 0 {
+                Err(Error::from(ret))
+            } else {
+                Ok(DataProps { props })
+            }
+        }
+    }
 }
 
 impl<A: PictureAllocator> Drop for Decoder<A> {
@@ -800,6 +1069,55 @@ impl<A: PictureAllocator> Drop for Decoder<A> {
 unsafe impl<A: PictureAllocator> Send for Decoder<A> {}
 unsafe impl<A: PictureAllocator> Sync for Decoder<A> {}
 
+/// Properties of the input data passed to [`Decoder::send_data`].
+#[derive(Debug)]
+pub struct DataProps {
+    props: Dav1dDataProps,
+}
+
+impl DataProps {
+    /// Timestamp of the input data.
+    pub fn timestamp(&self) -> Option<i64> {
+        let ts = self.props.timestamp;
+        if ts == i64::MIN {
+            None
+        } else {
+            Some(ts)
+        }
+    }
+
+    /// Duration of the input data or `0` if none was provided.
+    pub fn duration(&self) -> i64 {
+        self.props.duration
+    }
+
+    /// Offset of the input data or `-1` if none was provided.
+    pub fn offset(&self) -> i64 {
+        self.props.offset
+    }
+
+    /// Size of the input data in bytes.
+    pub fn size(&self) -> usize {
+        self.props.size
+    }
+
+    /// User data passed to [`Decoder::send_data_with_user_data`], if it is of type `T`.
+    pub fn user_data<T: Any>(&self) -> Option<&T> {
+        unsafe { user_data(&self.props.user_data) }
+    }
+}
+
+unsafe impl Send for DataProps {}
+unsafe impl Sync for DataProps {}
+
+impl Drop for DataProps {
+    fn drop(&mut self) {
+        unsafe {
+            dav1d_data_props_unref(&mut self.props);
+        }
+    }
+}
+
 #[derive(Debug)]
 struct InnerPicture {
     pub pic: Dav1dPicture,
@@ -899,10 +1217,132 @@ impl<A: PictureAllocator> std::ops::Deref for Plane<A> {
 
 static_assertions::assert_impl_all!(Plane<DefaultAllocator>: Send, Sync, Clone, Debug);
 
+mod sealed {
+    pub trait Sealed {}
+
+    impl Sealed for u8 {}
+    impl Sealed for u16 {}
+}
+
+/// Integer type used for storing the samples of a plane.
+///
+/// This is `u8` for 8 bit frames and `u16` for frames with a higher bit depth.
+pub trait Sample: sealed::Sealed + Copy + Send + Sync + 'static {
+    #[doc(hidden)]
+    fn matches_bit_depth(bit_depth: usize) -> bool;
+}
+
+impl Sample for u8 {
+    fn matches_bit_depth(bit_depth: usize) -> bool {
+        bit_depth == 8
+    }
+}
+
+impl Sample for u16 {
+    fn matches_bit_depth(bit_depth: usize) -> bool {
+        bit_depth > 8
+    }
+}
+
+/// Typed view of the visible samples of a single plane of a decoded frame.
+///
+/// Created by [`Picture::plane_view`].
+#[derive(Debug, Clone, Copy)]
+pub struct PlaneView<'a, T: Sample> {
+    data: &'a [T],
+    width: usize,
+    height: usize,
+    stride: usize,
+}
+
+impl<'a, T: Sample> PlaneView<'a, T> {
+    /// Width of the plane in samples.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Height of the plane in samples.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Stride of the plane in samples.
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    /// Row `y` of the plane without stride padding.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `y` is not smaller than the height of the plane.
+    pub fn row(&self, y: usize) -> &'a [T] {
+        assert!(y < self.height, "Row {} out of bounds", y);
+        &self.data[y * self.stride..][..self.width]
+    }
+
+    /// Iterator over all rows of the plane without stride padding.
+    pub fn rows(&self) -> impl ExactSizeIterator<Item = &'a [T]> + DoubleEndedIterator + 'a {
+        let width = self.width;
+        self.data
+            .chunks(self.stride)
+            .take(self.height)
+            .map(move |row| &row[..width])
+    }
+
+    /// Sample at position `x` and `y`, or `None` if the position is outside the plane.
+    pub fn get(&self, x: usize, y: usize) -> Option<T> {
+        if x < self.width && y < self.height {
+            Some(self.data[y * self.stride + x])
+        } else {
+            None
+        }
+    }
+}
+
+static_assertions::assert_impl_all!(PlaneView<'static, u16>: Send, Sync, Clone, Copy, Debug);
+
 /// Number of bits per component.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct BitsPerComponent(pub usize);
 
+/// Geometry of a single plane of a decoded frame.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PlaneGeometry {
+    /// Width of the plane in samples.
+    pub width: usize,
+    /// Height of the plane in samples.
+    pub height: usize,
+    /// Number of bytes between two rows.
+    pub stride: usize,
+    /// Number of bytes per sample, 1 for 8 bit frames and 2 otherwise.
+    pub bytes_per_sample: usize,
+    /// Horizontal subsampling of the plane as power of two.
+    pub x_shift: u32,
+    /// Vertical subsampling of the plane as power of two.
+    pub y_shift: u32,
+}
+
+impl PlaneGeometry {
+    fn new(
+        frame_width: u32,
+        frame_height: u32,
+        stride: u32,
+        bit_depth: usize,
+        x_shift: u32,
+        y_shift: u32,
+    ) -> Self {
+        PlaneGeometry {
+            width: (frame_width as usize + (1 << x_shift) - 1) >> x_shift,
+            height: (frame_height as usize + (1 << y_shift) - 1) >> y_shift,
+            stride: stride as usize,
+            bytes_per_sample: if bit_depth > 8 { 2 } else { 1 },
+            x_shift,
+            y_shift,
+        }
+    }
+}
+
 impl<A: PictureAllocator> Picture<A> {
     /// Stride in pixels of the `component` for the decoded frame.
     pub fn stride(&self, component: PlanarImageComponent) -> u32 {
@@ -921,16 +1361,38 @@ impl<A: PictureAllocator> Picture<A> {
 
     /// Plane geometry of the `component` for the decoded frame.
     ///
-    /// This returns the stride and height.
+    /// This returns the stride and height, see [`Picture::plane_geometry`] for the complete
+    /// geometry. For the chroma components of monochrome frames this returns the height of the
+    /// frame, use [`Picture::plane_geometry`] to check whether the plane exists.
     pub fn plane_data_geometry(&self, component: PlanarImageComponent) -> (u32, u32) {
-        let height = match component {
-            PlanarImageComponent::Y => self.height(),
-            _ => match self.pixel_layout() {
-                PixelLayout::I420 => (self.height() + 1) / 2,
-                PixelLayout::I400 | PixelLayout::I422 | PixelLayout::I444 => self.height(),
+        match self.plane_geometry(component) {
+            Some(geometry) => (geometry.stride as u32, geometry.height as u32),
+            None => (self.stride(component), self.height()),
+        }
+    }
+
+    /// Geometry of the visible samples of the `component` for the decoded frame.
+    ///
+    /// The chroma subsampling is taken from the sequence header. Returns `None` for the chroma
+    /// components of monochrome frames.
+    pub fn plane_geometry(&self, component: PlanarImageComponent) -> Option<PlaneGeometry> {
+        let (x_shift, y_shift) = match component {
+            PlanarImageComponent::Y => (0, 0),
+            _ if self.pixel_layout() == PixelLayout::I400 => return None,
+            _ => unsafe {
+                let seq_hdr = &*self.inner.pic.seq_hdr;
+                (seq_hdr.ss_hor as u32, seq_hdr.ss_ver as u32)
             },
         };
-        (self.stride(component), height)
+
+        Some(PlaneGeometry::new(
+            self.width(),
+            self.height(),
+            self.stride(component),
+            self.bit_depth(),
+            x_shift,
+            y_shift,
+        ))
     }
 
     /// Plane data of the `component` for the decoded frame.
@@ -938,6 +1400,40 @@ impl<A: PictureAllocator> Picture<A> {
         Plane(self.clone(), component)
     }
 
+    /// Typed view of the visible samples of the `component` for the decoded frame.
+    ///
+    /// `T` must be `u8` for 8 bit frames and `u16` for frames with a higher bit depth, otherwise
+    /// [`Error::InvalidArgument`] is returned. The same is returned for the chroma components of
+    /// monochrome frames.
+    pub fn plane_view<T: Sample>(
+        &self,
+        component: PlanarImageComponent,
+    ) -> Result<PlaneView<'_, T>, Error> {
+        if !T::matches_bit_depth(self.bit_depth()) {
+            return Err(Error::InvalidArgument);
+        }
+
+        let geometry = self
+            .plane_geometry(component)
+            .ok_or(Error::InvalidArgument)?;
+        let data = self.plane_data_ptr(component) as *const T;
+        if geometry.stride == 0 || data.is_null() {
+            return Err(Error::InvalidArgument);
+        }
+
+        let stride = geometry.stride / mem::size_of::<T>();
+        let height = geometry.height;
+
+        Ok(PlaneView {
+            // SAFETY: The plane data is aligned to PICTURE_ALIGNMENT bytes, stays alive as long as
+            // the picture and contains `stride` bytes for each of the `height` rows.
+            data: unsafe { std::slice::from_raw_parts(data, stride * height) },
+            width: geometry.width,
+            height,
+            stride,
+        })
+    }
+
     /// Bit depth of the plane data.
     ///
     /// This returns 8 or 16 for the underlying integer type used for the plane data.
@@ -951,14 +1447,7 @@ impl<A: PictureAllocator> Picture<A> {
     ///
     /// Check [`Picture::bit_depth`] for the number of storage bits.
     pub fn bits_per_component(&self) -> Option<BitsPerComponent> {
-        unsafe {
-            match (*self.inner.pic.seq_hdr).hbd {
-                0 => Some(BitsPerComponent(8)),
-                1 => Some(BitsPerComponent(10)),
-                2 => Some(BitsPerComponent(12)),
-                _ => None,
-            }
-        }
+        unsafe { bits_per_component((*self.inner.pic.seq_hdr).hbd) }
     }
 
     /// Width of the frame.
@@ -973,14 +1462,7 @@ impl<A: PictureAllocator> Picture<A> {
 
     /// Pixel layout of the frame.
     pub fn pixel_layout(&self) -> PixelLayout {
-        #[allow(non_upper_case_globals)]
-        match self.inner.pic.p.layout {
-            DAV1D_PIXEL_LAYOUT_I400 => PixelLayout::I400,
-            DAV1D_PIXEL_LAYOUT_I420 => PixelLayout::I420,
-            DAV1D_PIXEL_LAYOUT_I422 => PixelLayout::I422,
-            DAV1D_PIXEL_LAYOUT_I444 => PixelLayout::I444,
-            _ => unreachable!(),
-        }
+        pixel_layout(self.inner.pic.p.layout)
     }
 
     /// Timestamp of the frame.
@@ -1011,105 +1493,141 @@ impl<A: PictureAllocator> Picture<A> {
         self.inner.pic.m.offset
     }
 
+    /// User data of the frame, if it is of type `T`.
+    ///
+    /// This is the same user data as the one provided to [`Decoder::send_data_with_user_data`].
+    pub fn user_data<T: Any>(&self) -> Option<&T> {
+        unsafe { user_data(&self.inner.pic.m.user_data) }
+    }
+
     /// Chromaticity coordinates of the source colour primaries.
     pub fn color_primaries(&self) -> pixel::ColorPrimaries {
-        unsafe {
-            #[allow(non_upper_case_globals)]
-            match (*self.inner.pic.seq_hdr).pri {
-                DAV1D_COLOR_PRI_BT709 => pixel::ColorPrimaries::BT709,
-                DAV1D_COLOR_PRI_UNKNOWN => pixel::ColorPrimaries::Unspecified,
-                DAV1D_COLOR_PRI_BT470M => pixel::ColorPrimaries::BT470M,
-                DAV1D_COLOR_PRI_BT470BG => pixel::ColorPrimaries::BT470BG,
-                DAV1D_COLOR_PRI_BT601 => pixel::ColorPrimaries::BT470BG,
-                DAV1D_COLOR_PRI_SMPTE240 => pixel::ColorPrimaries::ST240M,
-                DAV1D_COLOR_PRI_FILM => pixel::ColorPrimaries::Film,
-                DAV1D_COLOR_PRI_BT2020 => pixel::ColorPrimaries::BT2020,
-                DAV1D_COLOR_PRI_XYZ => pixel::ColorPrimaries::ST428,
-                DAV1D_COLOR_PRI_SMPTE431 => pixel::ColorPrimaries::P3DCI,
-                DAV1D_COLOR_PRI_SMPTE432 => pixel::ColorPrimaries::P3Display,
-                DAV1D_COLOR_PRI_EBU3213 => pixel::ColorPrimaries::Tech3213,
-                23..=DAV1D_COLOR_PRI_RESERVED => pixel::ColorPrimaries::Unspecified,
-                _ => unreachable!(),
-            }
-        }
+        unsafe { color_primaries((*self.inner.pic.seq_hdr).pri) }
     }
 
     /// Transfer characteristics function.
     pub fn transfer_characteristic(&self) -> pixel::TransferCharacteristic {
-        unsafe {
-            #[allow(non_upper_case_globals)]
-            match (*self.inner.pic.seq_hdr).trc {
-                DAV1D_TRC_BT709 => pixel::TransferCharacteristic::BT1886,
-                DAV1D_TRC_UNKNOWN => pixel::TransferCharacteristic::Unspecified,
-                DAV1D_TRC_BT470M => pixel::TransferCharacteristic::BT470M,
-                DAV1D_TRC_BT470BG => pixel::TransferCharacteristic::BT470BG,
-                DAV1D_TRC_BT601 => pixel::TransferCharacteristic::ST170M,
-                DAV1D_TRC_SMPTE240 => pixel::TransferCharacteristic::ST240M,
-                DAV1D_TRC_LINEAR => pixel::TransferCharacteristic::Linear,
-                DAV1D_TRC_LOG100 => pixel::TransferCharacteristic::Logarithmic100,
-                DAV1D_TRC_LOG100_SQRT10 => pixel::TransferCharacteristic::Logarithmic316,
-                DAV1D_TRC_IEC61966 => pixel::TransferCharacteristic::SRGB,
-                DAV1D_TRC_BT1361 => pixel::TransferCharacteristic::BT1886,
-                DAV1D_TRC_SRGB => pixel::TransferCharacteristic::SRGB,
-                DAV1D_TRC_BT2020_10BIT => pixel::TransferCharacteristic::BT2020Ten,
-                DAV1D_TRC_BT2020_12BIT => pixel::TransferCharacteristic::BT2020Twelve,
-                DAV1D_TRC_SMPTE2084 => pixel::TransferCharacteristic::PerceptualQuantizer,
-                DAV1D_TRC_SMPTE428 => pixel::TransferCharacteristic::ST428,
-                DAV1D_TRC_HLG => pixel::TransferCharacteristic::HybridLogGamma,
-                19..=DAV1D_TRC_RESERVED => pixel::TransferCharacteristic::Unspecified,
-                _ => unreachable!(),
-            }
-        }
+        unsafe { transfer_characteristic((*self.inner.pic.seq_hdr).trc) }
     }
 
     /// Matrix coefficients used in deriving luma and chroma signals from the
     /// green, blue and red or X, Y and Z primaries.
     pub fn matrix_coefficients(&self) -> pixel::MatrixCoefficients {
-        unsafe {
-            #[allow(non_upper_case_globals)]
-            match (*self.inner.pic.seq_hdr).mtrx {
-                DAV1D_MC_IDENTITY => pixel::MatrixCoefficients::Identity,
-                DAV1D_MC_BT709 => pixel::MatrixCoefficients::BT709,
-                DAV1D_MC_UNKNOWN => pixel::MatrixCoefficients::Unspecified,
-                DAV1D_MC_FCC => pixel::MatrixCoefficients::BT470M,
-                DAV1D_MC_BT470BG => pixel::MatrixCoefficients::BT470BG,
-                DAV1D_MC_BT601 => pixel::MatrixCoefficients::BT470BG,
-                DAV1D_MC_SMPTE240 => pixel::MatrixCoefficients::ST240M,
-                DAV1D_MC_SMPTE_YCGCO => pixel::MatrixCoefficients::YCgCo,
-                DAV1D_MC_BT2020_NCL => pixel::MatrixCoefficients::BT2020NonConstantLuminance,
-                DAV1D_MC_BT2020_CL => pixel::MatrixCoefficients::BT2020ConstantLuminance,
-                DAV1D_MC_SMPTE2085 => pixel::MatrixCoefficients::ST2085,
-                DAV1D_MC_CHROMAT_NCL => {
-                    pixel::MatrixCoefficients::ChromaticityDerivedNonConstantLuminance
-                }
-                DAV1D_MC_CHROMAT_CL => {
-                    pixel::MatrixCoefficients::ChromaticityDerivedConstantLuminance
-                }
-                DAV1D_MC_ICTCP => pixel::MatrixCoefficients::ICtCp,
-                15..=DAV1D_MC_RESERVED => pixel::MatrixCoefficients::Unspecified,
-                _ => unreachable!(),
-            }
-        }
+        unsafe { matrix_coefficients((*self.inner.pic.seq_hdr).mtrx) }
     }
 
     /// YUV color range.
     pub fn color_range(&self) -> pixel::YUVRange {
-        unsafe {
-            match (*self.inner.pic.seq_hdr).color_range {
-                0 => pixel::YUVRange::Limited,
-                _ => pixel::YUVRange::Full,
-            }
-        }
+        unsafe { color_range((*self.inner.pic.seq_hdr).color_range) }
     }
 
     /// Sample position for subsampled chroma.
     pub fn chroma_location(&self) -> pixel::ChromaLocation {
-        // According to y4m mapping declared in dav1d's output/y4m2.c and applied from FFmpeg's yuv4mpegdec.c
+        unsafe { chroma_location((*self.inner.pic.seq_hdr).chr) }
+    }
+
+    /// Sample position for subsampled chroma as signalled in the sequence header.
+    ///
+    /// This is `Unspecified`, `Left` for vertical or `TopLeft` for co-located chroma.
+    pub(crate) fn chroma_sample_position(&self) -> pixel::ChromaLocation {
+        unsafe { chroma_sample_position((*self.inner.pic.seq_hdr).chr) }
+    }
+
+    /// Converts the frame to RGB in `format` and stores it in `buf` with `stride` bytes per row.
+    ///
+    /// The conversion uses the matrix coefficients, color range and chroma sample position of the
+    /// frame. Monochrome frames are converted to gray.
+    ///
+    /// Returns [`Error::InvalidArgument`] if `stride` is too small for a row of the frame or `buf`
+    /// is smaller than [`RgbFormat::buffer_len`], or if that size overflows.
+    pub fn to_rgb(&self, buf: &mut [u8], stride: usize, format: RgbFormat) -> Result<(), Error> {
+        let planes = [
+            self.plane(PlanarImageComponent::Y),
+            self.plane(PlanarImageComponent::U),
+            self.plane(PlanarImageComponent::V),
+        ];
+        rgb::to_rgb(
+            &rgb::Source::from_picture(self, &planes),
+            buf,
+            stride,
+            format,
+        )
+    }
+
+    /// Packs the frame into the semi-planar `format`, with the luma plane stored in `luma` with
+    /// `luma_stride` bytes per row and the interleaved chroma plane stored in `chroma` with
+    /// `chroma_stride` bytes per row.
+    ///
+    /// Returns [`Error::InvalidArgument`] if the frame is not 4:2:0, its bit depth is not
+    /// supported by `format`, a stride is too small for a row of the frame or a buffer is smaller
+    /// than [`SemiPlanarFormat::luma_len`] or [`SemiPlanarFormat::chroma_len`], or if these sizes
+    /// overflow.
+    pub fn to_semi_planar(
+        &self,
+        luma: &mut [u8],
+        luma_stride: usize,
+        chroma: &mut [u8],
+        chroma_stride: usize,
+        format: SemiPlanarFormat,
+    ) -> Result<(), Error> {
+        semi_planar::pack(
+            self,
+            Some((luma, luma_stride)),
+            chroma,
+            chroma_stride,
+            format,
+        )
+    }
+
+    /// Packs only the interleaved chroma plane of the frame into the semi-planar `format`.
+    ///
+    /// This is useful together with a [`PictureAllocator`] that allocates the luma plane of the
+    /// frame directly in the destination buffer, which avoids copying the luma plane. As the luma
+    /// samples are left as decoded this is only supported for [`SemiPlanarFormat::Nv12`], the other
+    /// formats return [`Error::InvalidArgument`].
+    ///
+    /// See [`Picture::to_semi_planar`] for the other errors.
+    pub fn to_semi_planar_chroma(
+        &self,
+        chroma: &mut [u8],
+        chroma_stride: usize,
+        format: SemiPlanarFormat,
+    ) -> Result<(), Error> {
+        semi_planar::pack(self, None, chroma, chroma_stride, format)
+    }
+
+    /// Sequence header of the frame.
+    pub fn sequence_header(&self) -> SequenceHeader {
+        unsafe { SequenceHeader::from_raw(&*self.inner.pic.seq_hdr) }
+    }
+
+    /// Frame header of the frame.
+    pub fn frame_header(&self) -> FrameHeader {
+        unsafe { FrameHeader::from_raw(&*self.inner.pic.frame_hdr) }
+    }
+
+    /// Render width and height from the frame header.
+    pub(crate) fn render_size(&self) -> (u32, u32) {
         unsafe {
-            match (*self.inner.pic.seq_hdr).chr {
-                DAV1D_CHR_UNKNOWN | DAV1D_CHR_COLOCATED => pixel::ChromaLocation::Center,
-                DAV1D_CHR_VERTICAL => pixel::ChromaLocation::Left,
-                _ => unreachable!(),
+            let frame_hdr = &*self.inner.pic.frame_hdr;
+            (
+                frame_hdr.render_width as u32,
+                frame_hdr.render_height as u32,
+            )
+        }
+    }
+
+    /// Film grain parameters of the frame, if any.
+    ///
+    /// If the decoder was created with [`Settings::set_apply_grain`] enabled, these have already
+    /// been applied to the picture.
+    pub fn film_grain(&self) -> Option<FilmGrainData> {
+        unsafe {
+            let film_grain = &(*self.inner.pic.frame_hdr).film_grain;
+            if film_grain.present == 0 {
+                None
+            } else {
+                Some(FilmGrainData::from_raw(&film_grain.data))
             }
         }
     }
@@ -1156,6 +1674,20 @@ impl<A: PictureAllocator> Picture<A> {
             }
         }
     }
+
+    /// ITU-T T.35 metadata payloads attached to the frame.
+    ///
+    /// Each payload keeps a reference to the picture and can outlive it like a [`Plane`].
+    pub fn itut_t35(&self) -> impl ExactSizeIterator<Item = ItutT35<A>> {
+        let count = if self.inner.pic.itut_t35.is_null() {
+            0
+        } else {
+            self.inner.pic.n_itut_t34
+        };
+        let picture = self.clone();
+
+        (0..count).map(move |index| ItutT35(picture.clone(), index))
+    }
 }
 
 static_assertions::assert_impl_all!(Picture<DefaultAllocator>: Send, Sync, Clone, Debug);
@@ -1173,77 +1705,17 @@ impl Drop for InnerPicture {
 
 #[cfg(test)]
 mod test {
+    use super::ivf;
     use std::{
         collections::HashSet,
         fmt, ptr,
         sync::{self, atomic},
     };
 
-    mod ivf {
-        use bitstream_io::{ByteRead, ByteReader, LittleEndian};
-        use std::io;
-
-        #[derive(Debug, PartialEq, Eq)]
-        pub struct Header {
-            pub tag: [u8; 4],
-            pub w: u16,
-            pub h: u16,
-            pub timebase_num: u32,
-            pub timebase_den: u32,
-        }
-
-        pub fn read_header(r: &mut dyn io::Read) -> io::Result<Header> {
-            let mut br = ByteReader::endian(r, LittleEndian);
-
-            let mut signature = [0u8; 4];
-            let mut tag = [0u8; 4];
-
-            br.read_bytes(&mut signature)?;
-            let _v0 = br.read::<u16>()?;
-            let _v1 = br.read::<u16>()?;
-            br.read_bytes(&mut tag)?;
-
-            let w = br.read::<u16>()?;
-            let h = br.read::<u16>()?;
-
-            let timebase_den = br.read::<u32>()?;
-            let timebase_num = br.read::<u32>()?;
-
-            let _ = br.read::<u32>()?;
-            let _ = br.read::<u32>()?;
-
-            Ok(Header {
-                tag,
-                w,
-                h,
-                timebase_num,
-                timebase_den,
-            })
-        }
-
-        pub struct Packet {
-            pub data: Box<[u8]>,
-            pub pts: u64,
-        }
-
-        pub fn read_packet(r: &mut dyn io::Read) -> io::Result<Packet> {
-            let mut br = ByteReader::endian(r, LittleEndian);
-
-            let len = br.read::<u32>()?;
-            let pts = br.read::<u64>()?;
-            let mut buf = vec![0u8; len as usize];
-
-            br.read_bytes(&mut buf)?;
-
-            Ok(Packet {
-                data: buf.into_boxed_slice(),
-                pts,
-            })
-        }
-    }
-
     static TEST_FILE_420_8: &[u8] = include_bytes!("../test-420-8.ivf");
     static TEST_FILE_420_12: &[u8] = include_bytes!("../test-420-12.ivf");
+    // First frame of `test-420-8.ivf` with film grain parameters added
+    static TEST_FILE_420_8_GRAIN: &[u8] = include_bytes!("../test-420-8-grain.ivf");
 
     fn handle_pending_pictures<A: super::PictureAllocator + fmt::Debug>(
         dec: &mut super::Decoder<A>,
@@ -1299,6 +1771,30 @@ mod test {
                 picture.chroma_location(),
                 super::pixel::ChromaLocation::Center,
             );
+            let seq_hdr = picture.sequence_header();
+            assert_eq!(seq_hdr.profile, if bpp == 12 { 2 } else { 0 });
+            assert_eq!(seq_hdr.max_width, 320);
+            assert_eq!(seq_hdr.max_height, 240);
+            assert_eq!(seq_hdr.pixel_layout, super::PixelLayout::I420);
+            assert_eq!(
+                seq_hdr.bits_per_component,
+                Some(super::BitsPerComponent(bpp))
+            );
+            assert_eq!((seq_hdr.ss_hor, seq_hdr.ss_ver), (1, 1));
+            assert!(!seq_hdr.operating_points.is_empty());
+            assert!(!seq_hdr.still_picture);
+
+            let frame_hdr = picture.frame_header();
+            assert_eq!(frame_hdr.coded_width, 320);
+            assert_eq!(frame_hdr.height, 240);
+            assert!(frame_hdr.show_frame || frame_hdr.show_existing_frame);
+            if i == 0 {
+                assert_eq!(frame_hdr.frame_type, super::headers::FrameType::Key);
+                assert_eq!(frame_hdr.primary_ref_frame, None);
+            }
+
+            assert_eq!(picture.itut_t35().len(), 0);
+
             assert_eq!(picture.timestamp(), Some(pts[i]));
             assert_eq!(picture.offset(), i as i64);
 
@@ -1344,20 +1840,26 @@ mod test {
         }
     }
 
-    fn decode_file<A: super::PictureAllocator + fmt::Debug>(
+    pub(crate) fn decode_file<A: super::PictureAllocator + fmt::Debug>(
         file: &[u8],
         mut dec: super::Decoder<A>,
         pictures: &mut Vec<super::Picture<A>>,
     ) {
-        use std::io;
+        decode_file_with(file, &mut dec, pictures);
+    }
 
-        let mut r = io::BufReader::new(file);
-        let header = ivf::read_header(&mut r).unwrap();
+    fn decode_file_with<A: super::PictureAllocator + fmt::Debug>(
+        file: &[u8],
+        dec: &mut super::Decoder<A>,
+        pictures: &mut Vec<super::Picture<A>>,
+    ) {
+        let mut r = ivf::Reader::new(file).unwrap();
+        let header = *r.header();
         println!("{:?}", header);
 
         let mut idx = 0;
 
-        while let Ok(packet) = ivf::read_packet(&mut r) {
+        while let Some(packet) = r.read_packet().unwrap() {
             println!("Packet {}", packet.pts);
 
             // Let's use millisecond timestamps
@@ -1371,7 +1873,7 @@ mod test {
                     // pending pictures and send pending data to the decoder
                     // until it is all used up.
                     loop {
-                        handle_pending_pictures(&mut dec, pictures, false);
+                        handle_pending_pictures(dec, pictures, false);
 
                         match dec.send_pending_data() {
                             Err(e) if e.is_again() => continue,
@@ -1389,13 +1891,13 @@ mod test {
             }
 
             // Handle all pending pictures before sending the next data.
-            handle_pending_pictures(&mut dec, pictures, false);
+            handle_pending_pictures(dec, pictures, false);
 
             idx += 1;
         }
 
         // Handle all pending pictures that were not output yet.
-        handle_pending_pictures(&mut dec, pictures, true);
+        handle_pending_pictures(dec, pictures, true);
     }
 
     #[test]
@@ -1414,6 +1916,419 @@ mod test {
         check_pictures(&pictures, 12);
     }
 
+    #[test]
+    fn test_to_rgb() {
+        for file in [TEST_FILE_420_8, TEST_FILE_420_12] {
+            let dec = super::Decoder::new().expect("failed to create decoder instance");
+            let mut pictures = vec![];
+            decode_file(file, dec, &mut pictures);
+
+            let mut rgba = vec![0; super::RgbFormat::Rgba.buffer_len(240, 320 * 4).unwrap()];
+            pictures[0]
+                .to_rgb(&mut rgba, 320 * 4, super::RgbFormat::Rgba)
+                .unwrap();
+            assert!(rgba.chunks_exact(4).all(|p| p[3] == 255));
+
+            let mut rgb = vec![0; super::RgbFormat::Rgb24.buffer_len(240, 320 * 3).unwrap()];
+            pictures[0]
+                .to_rgb(&mut rgb, 320 * 3, super::RgbFormat::Rgb24)
+                .unwrap();
+            assert_eq!(
+                pictures[0].to_rgb(&mut rgb, 320 * 3 - 1, super::RgbFormat::Rgb24),
+                Err(super::Error::InvalidArgument)
+            );
+            assert!(rgb
+                .chunks_exact(3)
+                .zip(rgba.chunks_exact(4))
+                .all(|(rgb, rgba)| rgb == &rgba[..3]));
+        }
+    }
+
+    #[test]
+    fn test_plane_geometry() {
+        use super::PlaneGeometry;
+
+        // Odd dimensions round up for subsampled planes
+        assert_eq!(
+            PlaneGeometry::new(321, 241, 704, 10, 1, 0),
+            PlaneGeometry {
+                width: 161,
+                height: 241,
+                stride: 704,
+                bytes_per_sample: 2,
+                x_shift: 1,
+                y_shift: 0,
+            }
+        );
+        assert_eq!(PlaneGeometry::new(321, 241, 384, 8, 1, 1).height, 121);
+        assert_eq!(PlaneGeometry::new(321, 241, 384, 8, 0, 0).width, 321);
+
+        let dec = super::Decoder::new().expect("failed to create decoder instance");
+        let mut pictures = vec![];
+        decode_file(TEST_FILE_420_12, dec, &mut pictures);
+        let picture = &pictures[0];
+
+        let y = picture
+            .plane_geometry(super::PlanarImageComponent::Y)
+            .unwrap();
+        assert_eq!((y.width, y.height, y.x_shift, y.y_shift), (320, 240, 0, 0));
+        assert_eq!(y.bytes_per_sample, 2);
+        let v = picture
+            .plane_geometry(super::PlanarImageComponent::V)
+            .unwrap();
+        assert_eq!((v.width, v.height, v.x_shift, v.y_shift), (160, 120, 1, 1));
+        assert_eq!(
+            v.stride,
+            picture.stride(super::PlanarImageComponent::V) as usize
+        );
+    }
+
+    #[test]
+    fn test_plane_view() {
+        use super::PlanarImageComponent::{U, Y};
+
+        let dec = super::Decoder::new().expect("failed to create decoder instance");
+        let mut pictures = vec![];
+        decode_file(TEST_FILE_420_8, dec, &mut pictures);
+        let picture = &pictures[0];
+
+        assert_eq!(
+            picture.plane_view::<u16>(Y).unwrap_err(),
+            super::Error::InvalidArgument
+        );
+        let view = picture.plane_view::<u8>(U).unwrap();
+        assert_eq!((view.width(), view.height()), (160, 120));
+        assert_eq!(view.stride(), picture.stride(U) as usize);
+        assert_eq!(view.rows().len(), 120);
+        assert!(view.rows().all(|row| row.len() == 160));
+        assert_eq!(view.row(1), &picture.plane(U)[view.stride()..][..160]);
+        assert_eq!(view.get(159, 119), Some(view.row(119)[159]));
+        assert_eq!(view.get(160, 0), None);
+        assert_eq!(view.get(0, 120), None);
+
+        let dec = super::Decoder::new().expect("failed to create decoder instance");
+        let mut pictures = vec![];
+        decode_file(TEST_FILE_420_12, dec, &mut pictures);
+        let picture = &pictures[0];
+
+        assert_eq!(
+            picture.plane_view::<u8>(Y).unwrap_err(),
+            super::Error::InvalidArgument
+        );
+        let view = picture.plane_view::<u16>(Y).unwrap();
+        assert_eq!((view.width(), view.height()), (320, 240));
+        assert_eq!(view.stride(), picture.stride(Y) as usize / 2);
+        let plane = picture.plane(Y);
+        assert_eq!(
+            view.get(1, 0),
+            Some(u16::from_ne_bytes([plane[2], plane[3]]))
+        );
+        assert!(view.rows().flatten().all(|&v| v < 4096));
+    }
+
+    #[test]
+    fn test_event_flags() {
+        let mut settings = super::Settings::new();
+        settings.set_n_threads(1);
+        settings.set_max_frame_delay(1);
+        let mut dec =
+            super::Decoder::with_settings(&settings).expect("failed to create decoder instance");
+        assert_eq!(dec.event_flags(), Ok(super::EventFlags::empty()));
+
+        let mut r = ivf::Reader::new(TEST_FILE_420_8).unwrap();
+        let packet = r.read_packet().unwrap().unwrap();
+
+        let mut pictures = vec![];
+        match dec.send_data(packet.data, None, None, None) {
+            Err(e) if e.is_again() => (),
+            res => res.unwrap(),
+        }
+        handle_pending_pictures(&mut dec, &mut pictures, true);
+        assert_eq!(pictures.len(), 1);
+
+        assert!(dec
+            .event_flags()
+            .unwrap()
+            .contains(super::EventFlags::NEW_SEQUENCE));
+        assert_eq!(dec.event_flags(), Ok(super::EventFlags::empty()));
+    }
+
+    #[test]
+    fn test_decode_error_data_props() {
+        let mut dec = super::Decoder::new().expect("failed to create decoder instance");
+
+        // Sequence header OBU with an invalid profile
+        let res = dec.send_data([0x0Au8, 0x01, 0xFF], Some(42), Some(1000), Some(33));
+        assert_eq!(res, Err(super::Error::InvalidArgument));
+
+        let props = dec.decode_error_data_props().unwrap();
+        assert_eq!(props.offset(), 42);
+        assert_eq!(props.timestamp(), Some(1000));
+        assert_eq!(props.duration(), 33);
+        assert_eq!(props.size(), 3);
+    }
+
+    #[test]
+    fn test_apply_grain() {
+        let mut settings = super::Settings::new();
+        settings.set_apply_grain(false);
+
+        let mut dec =
+            super::Decoder::with_settings(&settings).expect("failed to create decoder instance");
+        let mut pictures = vec![];
+        decode_file_with(TEST_FILE_420_8, &mut dec, &mut pictures);
+
+        let grain_pictures = pictures
+            .iter()
+            .map(|picture| {
+                assert_eq!(picture.film_grain(), picture.frame_header().film_grain);
+                dec.apply_grain(picture).unwrap()
+            })
+            .collect::<Vec<_>>();
+        check_pictures(&grain_pictures, 8);
+
+        let mut dec =
+            super::Decoder::with_settings(&settings).expect("failed to create decoder instance");
+        let mut pictures = vec![];
+        decode_file_with(TEST_FILE_420_8_GRAIN, &mut dec, &mut pictures);
+        assert_eq!(pictures.len(), 1);
+
+        let film_grain = pictures[0].film_grain().expect("film grain");
+        assert_eq!(film_grain.seed, 1234);
+        assert_eq!(film_grain.y_points, [[0, 64], [255, 64]]);
+
+        // The grain is only applied to the new picture
+        let luma = |picture: &super::Picture| {
+            let plane = picture.plane(super::PlanarImageComponent::Y);
+            let stride = picture.stride(super::PlanarImageComponent::Y) as usize;
+            plane
+                .chunks(stride)
+                .flat_map(|row| &row[..picture.width() as usize])
+                .copied()
+                .collect::<Vec<_>>()
+        };
+        let grain_picture = dec.apply_grain(&pictures[0]).unwrap();
+        assert_ne!(luma(&pictures[0]), luma(&grain_picture));
+
+        // Same as applying the grain while decoding
+        let mut pictures = vec![];
+        decode_file(
+            TEST_FILE_420_8_GRAIN,
+            super::Decoder::new().unwrap(),
+            &mut pictures,
+        );
+        assert_eq!(luma(&pictures[0]), luma(&grain_picture));
+    }
+
+    #[test]
+    fn test_logger() {
+        let messages = sync::Arc::new(sync::Mutex::new(Vec::new()));
+
+        let mut settings = super::Settings::new();
+        let messages_clone = messages.clone();
+        settings.set_logger(move |msg| messages_clone.lock().unwrap().push(msg.to_owned()));
+
+        let mut dec =
+            super::Decoder::with_settings(&settings).expect("failed to create decoder instance");
+        drop(settings);
+
+        // Sequence header OBU with an invalid profile
+        assert!(dec
+            .send_data([0x0Au8, 0x01, 0xFF], None, None, None)
+            .is_err());
+        drop(dec);
+
+        let messages = messages.lock().unwrap();
+        assert!(!messages.is_empty());
+        assert!(messages.iter().all(|msg| !msg.ends_with('\n')));
+
+        let mut settings = super::Settings::new();
+        settings.disable_logger();
+        let mut dec =
+            super::Decoder::with_settings(&settings).expect("failed to create decoder instance");
+        assert!(dec
+            .send_data([0x0Au8, 0x01, 0xFF], None, None, None)
+            .is_err());
+    }
+
+    #[test]
+    fn test_version() {
+        assert!(!super::version().is_empty());
+
+        let api_version = super::api_version();
+        assert!(api_version >= super::ApiVersion::new(6, 0, 0));
+        assert!(api_version < super::ApiVersion::new(api_version.major + 1, 0, 0));
+        assert!(super::ApiVersion::new(6, 9, 0) > super::ApiVersion::new(6, 8, 1));
+        assert_eq!(super::ApiVersion::new(7, 0, 1).to_string(), "7.0.1");
+    }
+
+    #[test]
+    fn test_user_data() {
+        #[derive(Debug, PartialEq, Eq)]
+        struct PacketInfo {
+            id: usize,
+        }
+
+        let mut dec = super::Decoder::new().expect("failed to create decoder instance");
+        let mut pictures = vec![];
+
+        let mut r = ivf::Reader::new(TEST_FILE_420_8).unwrap();
+
+        let mut id = 0;
+        while let Some(packet) = r.read_packet().unwrap() {
+            match dec.send_data_with_user_data(
+                packet.data,
+                Some(id as i64),
+                None,
+                None,
+                PacketInfo { id },
+            ) {
+                Err(e) if e.is_again() => loop {
+                    handle_pending_pictures(&mut dec, &mut pictures, false);
+                    match dec.send_pending_data() {
+                        Err(e) if e.is_again() => continue,
+                        res => break res.unwrap(),
+                    }
+                },
+                res => res.unwrap(),
+            }
+            handle_pending_pictures(&mut dec, &mut pictures, false);
+            id += 1;
+        }
+        handle_pending_pictures(&mut dec, &mut pictures, true);
+
+        assert_eq!(pictures.len(), 5);
+        for picture in &pictures {
+            let info = picture.user_data::<PacketInfo>().unwrap();
+            assert_eq!(info.id as i64, picture.offset());
+            assert_eq!(picture.user_data::<u32>(), None);
+        }
+
+        let res = dec.send_data_with_user_data([0x0Au8, 0x01, 0xFF], None, None, None, "corrupt");
+        assert!(res.is_err());
+        let props = dec.decode_error_data_props().unwrap();
+        assert_eq!(props.user_data::<&str>(), Some(&"corrupt"));
+    }
+
+    #[test]
+    fn test_parse_sequence_header() {
+        let mut r = ivf::Reader::new(TEST_FILE_420_12).unwrap();
+        let packet = r.read_packet().unwrap().unwrap();
+
+        let seq_hdr = super::parse_sequence_header(&packet.data).unwrap();
+        assert_eq!(seq_hdr.profile, 2);
+        assert_eq!(seq_hdr.max_width, 320);
+        assert_eq!(seq_hdr.max_height, 240);
+        assert_eq!(seq_hdr.pixel_layout, super::PixelLayout::I420);
+        assert_eq!(
+            seq_hdr.bits_per_component,
+            Some(super::BitsPerComponent(12))
+        );
+        assert_eq!(seq_hdr.color_primaries, super::pixel::ColorPrimaries::BT709);
+
+        assert!(super::parse_sequence_header(&[]).is_err());
+    }
+
+    #[test]
+    fn test_parse_sequence_header_reserved() {
+        // Reduced still picture sequence header with reserved color primaries and chroma sample
+        // position
+        let fields: &[(u32, u32)] = &[
+            (0, 3),   // seq_profile
+            (1, 1),   // still_picture
+            (1, 1),   // reduced_still_picture_header
+            (0, 5),   // seq_level_idx
+            (8, 4),   // frame_width_bits_minus_1
+            (7, 4),   // frame_height_bits_minus_1
+            (319, 9), // max_frame_width_minus_1
+            (239, 8), // max_frame_height_minus_1
+            (0, 6),   // use_128x128_superblock ... enable_restoration
+            (0, 2),   // high_bitdepth, mono_chrome
+            (1, 1),   // color_description_present_flag
+            (0, 8),   // color_primaries
+            (1, 8),   // transfer_characteristics
+            (1, 8),   // matrix_coefficients
+            (0, 1),   // color_range
+            (3, 2),   // chroma_sample_position
+            (0, 2),   // separate_uv_delta_q, film_grain_params_present
+            (1, 1),   // trailing_one_bit
+        ];
+
+        let mut payload = Vec::new();
+        let mut bit = 0;
+        for &(value, bits) in fields {
+            for i in (0..bits).rev() {
+                if bit % 8 == 0 {
+                    payload.push(0u8);
+                }
+                *payload.last_mut().unwrap() |= (((value >> i) & 1) as u8) << (7 - bit % 8);
+                bit += 1;
+            }
+        }
+        let obu = [&[0x0a, payload.len() as u8][..], &payload].concat();
+
+        let seq_hdr = super::parse_sequence_header(&obu).unwrap();
+        assert_eq!(seq_hdr.max_width, 320);
+        assert_eq!(seq_hdr.max_height, 240);
+        assert_eq!(
+            seq_hdr.color_primaries,
+            super::pixel::ColorPrimaries::Unspecified
+        );
+        assert_eq!(
+            seq_hdr.transfer_characteristic,
+            super::pixel::TransferCharacteristic::BT1886
+        );
+        assert_eq!(
+            seq_hdr.chroma_location,
+            super::pixel::ChromaLocation::Unspecified
+        );
+    }
+
+    #[test]
+    fn test_itut_t35() {
+        let mut r = ivf::Reader::new(TEST_FILE_420_8).unwrap();
+        let packet = r.read_packet().unwrap().unwrap();
+
+        // HDR10+ metadata OBU after the temporal delimiter and sequence header OBUs
+        let metadata = [
+            0x2a, 0x09, 0x04, 0xb5, 0x00, 0x3c, 0x00, 0x01, 0x04, 0x01, 0x80,
+        ];
+        let data = [&packet.data[..18], &metadata, &packet.data[18..]].concat();
+        let mut w = ivf::Writer::new(Vec::new(), r.header()).unwrap();
+        w.write_packet(&data, 0).unwrap();
+        let file = w.into_inner().unwrap();
+
+        let mut pictures = vec![];
+        decode_file(&file, super::Decoder::new().unwrap(), &mut pictures);
+        let t35 = pictures.remove(0).itut_t35().collect::<Vec<_>>();
+        // The payloads keep the picture alive
+        drop(pictures);
+
+        assert_eq!(t35.len(), 1);
+        assert_eq!(t35[0].country_code(), 0xb5);
+        assert_eq!(t35[0].payload(), [0x00, 0x3c, 0x00, 0x01, 0x04, 0x01]);
+        assert!(t35[0].is_hdr10_plus());
+    }
+
+    #[test]
+    fn test_itut_t35_providers() {
+        let hdr10_plus = [0x00, 0x3C, 0x00, 0x01, 0x04, 0x01, 0x40];
+        assert_eq!(super::terminal_provider_code(&hdr10_plus), Some(0x003C));
+        assert!(super::is_hdr10_plus(0xB5, &hdr10_plus));
+        assert!(!super::is_hdr10_plus(0xB4, &hdr10_plus));
+        assert!(!super::is_dolby_vision(0xB5, &hdr10_plus));
+
+        let dolby_vision = [0x00, 0x3B, 0x00, 0x00, 0x08, 0x00, 0x37];
+        assert_eq!(super::terminal_provider_code(&dolby_vision), Some(0x003B));
+        assert!(super::is_dolby_vision(0xB5, &dolby_vision));
+        assert!(!super::is_hdr10_plus(0xB5, &dolby_vision));
+
+        let truncated = [0x00];
+        assert_eq!(super::terminal_provider_code(&truncated), None);
+        assert!(!super::is_hdr10_plus(0xB5, &truncated));
+        assert!(!super::is_dolby_vision(0xB5, &truncated));
+    }
+
     #[derive(Debug)]
     struct TestAllocator {
         counter: atomic::AtomicUsize,
@@ -1450,17 +2365,17 @@ mod test {
                 crate::PixelLayout::I420 => (
                     [
                         align(pic_params.width() as usize) * stride_mult,
-                        align((pic_params.width() as usize + 1) / 2) * stride_mult,
+                        align((pic_params.width() as usize).div_ceil(2)) * stride_mult,
                     ],
                     [
                         align(pic_params.height() as usize),
-                        align((pic_params.height() as usize + 1) / 2),
+                        align((pic_params.height() as usize).div_ceil(2)),
                     ],
                 ),
                 crate::PixelLayout::I422 => (
                     [
                         align(pic_params.width() as usize) * stride_mult,
-                        align((pic_params.width() as usize + 1) / 2) * stride_mult,
+                        align((pic_params.width() as usize).div_ceil(2)) * stride_mult,
                     ],
                     [
                         align(pic_params.height() as usize),
@@ -1599,3 +2514,88 @@ pub struct MasteringDisplay {
     /// Minimum luminance in candela per square metre as 18.14 fixed-point number.
     pub min_luminance: u32,
 }
+
+/// ITU-T T.35 country code of the United States.
+pub const ITUT_T35_COUNTRY_CODE_US: u8 = 0xB5;
+
+/// ITU-T T.35 terminal provider code of Samsung, used for HDR10+.
+pub const ITUT_T35_PROVIDER_CODE_SAMSUNG: u16 = 0x003C;
+
+/// ITU-T T.35 terminal provider code of Dolby.
+pub const ITUT_T35_PROVIDER_CODE_DOLBY: u16 = 0x003B;
+
+/// ITU-T T.35 metadata as carried in AV1 metadata OBUs.
+///
+/// The payload is kept alive by a reference to the picture it is attached to.
+#[derive(Debug)]
+pub struct ItutT35<A: PictureAllocator = DefaultAllocator>(Picture<A>, usize);
+
+impl<A: PictureAllocator> Clone for ItutT35<A> {
+    fn clone(&self) -> Self {
+        ItutT35(self.0.clone(), self.1)
+    }
+}
+
+impl<A: PictureAllocator> ItutT35<A> {
+    fn raw(&self) -> &Dav1dITUTT35 {
+        unsafe { &*self.0.inner.pic.itut_t35.add(self.1) }
+    }
+
+    /// Country code as specified in ITU-T T.35.
+    pub fn country_code(&self) -> u8 {
+        self.raw().country_code
+    }
+
+    /// Country code extension byte, only meaningful if the country code is `0xFF`.
+    pub fn country_code_extension_byte(&self) -> u8 {
+        self.raw().country_code_extension_byte
+    }
+
+    /// Payload bytes following the country code, starting with the terminal provider code.
+    pub fn payload(&self) -> &[u8] {
+        let t35 = self.raw();
+        if t35.payload.is_null() || t35.payload_size == 0 {
+            &[]
+        } else {
+            unsafe { std::slice::from_raw_parts(t35.payload, t35.payload_size) }
+        }
+    }
+
+    /// Terminal provider code of the payload, if it is long enough to contain one.
+    pub fn terminal_provider_code(&self) -> Option<u16> {
+        terminal_provider_code(self.payload())
+    }
+
+    /// Whether the payload is SMPTE ST 2094-40 (HDR10+) dynamic metadata.
+    pub fn is_hdr10_plus(&self) -> bool {
+        is_hdr10_plus(self.country_code(), self.payload())
+    }
+
+    /// Whether the payload is a Dolby Vision RPU.
+    pub fn is_dolby_vision(&self) -> bool {
+        is_dolby_vision(self.country_code(), self.payload())
+    }
+}
+
+static_assertions::assert_impl_all!(ItutT35<DefaultAllocator>: Send, Sync, Clone, Debug);
+
+fn terminal_provider_code(payload: &[u8]) -> Option<u16> {
+    match payload {
+        [hi, lo, ..] => Some(u16::from_be_bytes([*hi, *lo])),
+        _ => None,
+    }
+}
+
+fn is_hdr10_plus(country_code: u8, payload: &[u8]) -> bool {
+    // terminal_provider_oriented_code 0x0001 and application_identifier 4
+    country_code == ITUT_T35_COUNTRY_CODE_US
+        && terminal_provider_code(payload) == Some(ITUT_T35_PROVIDER_CODE_SAMSUNG)
+        && payload.get(2..5) == Some(&[0x00, 0x01, 0x04][..])
+}
+
+fn is_dolby_vision(country_code: u8, payload: &[u8]) -> bool {
+    // terminal_provider_oriented_code 0x00000800
+    country_code == ITUT_T35_COUNTRY_CODE_US
+        && terminal_provider_code(payload) == Some(ITUT_T35_PROVIDER_CODE_DOLBY)
+        && payload.get(2..6) == Some(&[0x00, 0x00, 0x08, 0x00][..])
+}
@@ -0,0 +1,42 @@
+use std::ffi::{c_char, c_int, c_void, CStr};
+use std::fmt;
+
+// `va_list` is passed as a pointer-sized value on all supported platforms, either because it is
+// a pointer, an array decaying to a pointer or a struct passed by reference.
+type VaList = *mut c_void;
+
+#[cfg_attr(
+    all(windows, target_env = "msvc"),
+    link(name = "legacy_stdio_definitions")
+)]
+extern "C" {
+    fn vsnprintf(s: *mut c_char, n: usize, format: *const c_char, ap: VaList) -> c_int;
+}
+
+// Messages longer than this are truncated, the `va_list` can only be consumed once.
+const MAX_MESSAGE_LEN: usize = 1024;
+
+/// Log callback passed to `dav1d` as the logger cookie.
+pub(crate) struct Logger(pub(crate) Box<dyn Fn(&str) + Send + Sync + 'static>);
+
+impl fmt::Debug for Logger {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.write_str("Logger")
+    }
+}
+
+pub(crate) unsafe extern "C" fn logger_callback(
+    cookie: *mut c_void,
+    format: *const c_char,
+    ap: VaList,
+) {
+    let logger = &*(cookie as *const Logger);
+
+    let mut buf = [0 as c_char; MAX_MESSAGE_LEN];
+    if vsnprintf(buf.as_mut_ptr(), buf.len(), format, ap) < 0 {
+        return;
+    }
+
+    let msg = CStr::from_ptr(buf.as_ptr()).to_string_lossy();
+    (logger.0)(msg.trim_end());
+}
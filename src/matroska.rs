@@ -0,0 +1,614 @@
+//! Demuxing of AV1 tracks from Matroska and WebM files.
+//!
+//! This is a minimal demuxer that only handles what is needed for feeding the first AV1 track
+//! of a file to the [`Decoder`](crate::Decoder). Elements are read sequentially, seeking is not
+//! supported.
+
+use crate::av1c::Av1CodecConfiguration;
+use crate::bmff::invalid_data;
+use std::io::{self, Read};
+
+// Element IDs
+const EBML: u32 = 0x1a45_dfa3;
+const DOC_TYPE: u32 = 0x4282;
+const SEGMENT: u32 = 0x1853_8067;
+const INFO: u32 = 0x1549_a966;
+const TIMESTAMP_SCALE: u32 = 0x2a_d7b1;
+const TRACKS: u32 = 0x1654_ae6b;
+const TRACK_ENTRY: u32 = 0xae;
+const TRACK_NUMBER: u32 = 0xd7;
+const CODEC_ID: u32 = 0x86;
+const CODEC_PRIVATE: u32 = 0x63a2;
+const VIDEO: u32 = 0xe0;
+const PIXEL_WIDTH: u32 = 0xb0;
+const PIXEL_HEIGHT: u32 = 0xba;
+const CLUSTER: u32 = 0x1f43_b675;
+const TIMESTAMP: u32 = 0xe7;
+const SIMPLE_BLOCK: u32 = 0xa3;
+const BLOCK_GROUP: u32 = 0xa0;
+const BLOCK: u32 = 0xa1;
+const BLOCK_DURATION: u32 = 0x9b;
+const REFERENCE_BLOCK: u32 = 0xfb;
+
+const DEFAULT_TIMESTAMP_SCALE: u64 = 1_000_000;
+const AV1_CODEC_ID: &str = "V_AV1";
+
+// Block flags
+const BLOCK_FLAG_KEYFRAME: u8 = 0x80;
+const BLOCK_FLAG_LACING: u8 = 0x06;
+
+/// AV1 track of a Matroska file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Track {
+    /// Track number as used by the blocks of the track.
+    pub number: u64,
+    /// Width of the encoded frames in pixels.
+    pub width: Option<u64>,
+    /// Height of the encoded frames in pixels.
+    pub height: Option<u64>,
+    /// Codec configuration from the `CodecPrivate` element.
+    pub codec_configuration: Option<Av1CodecConfiguration>,
+}
+
+/// A frame of the AV1 track.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Packet {
+    /// Frame data.
+    pub data: Box<[u8]>,
+    /// Presentation timestamp in nanoseconds.
+    pub timestamp: i64,
+    /// Duration in nanoseconds, if signalled.
+    pub duration: Option<u64>,
+    /// Whether the frame is a keyframe.
+    pub keyframe: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ElementHeader {
+    id: u32,
+    // `None` for elements of unknown size
+    size: Option<u64>,
+}
+
+/// Matroska and WebM file reader.
+#[derive(Debug)]
+pub struct Reader<R: Read> {
+    reader: R,
+    track: Track,
+    timestamp_scale: u64,
+    cluster_timestamp: u64,
+    pending: Option<ElementHeader>,
+}
+
+impl<R: Read> Reader<R> {
+    /// Creates a new [`Reader`] and reads the file headers up to the first cluster.
+    ///
+    /// Fails if the file contains no AV1 track.
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let header = read_element_header(&mut reader)?
+            .filter(|h| h.id == EBML)
+            .ok_or_else(|| invalid_data("No EBML header"))?;
+        let mut doc_type = None;
+        for_each_child(&read_element_data(&mut reader, header)?, |id, data| {
+            if id == DOC_TYPE {
+                doc_type = Some(read_string(data));
+            }
+            Ok(())
+        })?;
+        match doc_type.as_deref() {
+            Some("matroska") | Some("webm") => (),
+            _ => return Err(invalid_data("Unsupported EBML document type")),
+        }
+
+        read_element_header(&mut reader)?
+            .filter(|h| h.id == SEGMENT)
+            .ok_or_else(|| invalid_data("No segment"))?;
+
+        let mut timestamp_scale = DEFAULT_TIMESTAMP_SCALE;
+        let mut track = None;
+        let cluster = loop {
+            let header = read_element_header(&mut reader)?
+                .ok_or_else(|| invalid_data("No cluster found"))?;
+
+            match header.id {
+                INFO => {
+                    for_each_child(&read_element_data(&mut reader, header)?, |id, data| {
+                        if id == TIMESTAMP_SCALE {
+                            timestamp_scale = read_uint(data)?;
+                        }
+                        Ok(())
+                    })?;
+                }
+                TRACKS if track.is_none() => {
+                    track = parse_tracks(&read_element_data(&mut reader, header)?)?;
+                }
+                CLUSTER => break header,
+                _ => skip_element(&mut reader, header)?,
+            }
+        };
+
+        let track = track.ok_or_else(|| invalid_data("No AV1 track found"))?;
+
+        Ok(Reader {
+            reader,
+            track,
+            timestamp_scale,
+            cluster_timestamp: 0,
+            pending: Some(cluster),
+        })
+    }
+
+    /// The AV1 track that is read.
+    pub fn track(&self) -> &Track {
+        &self.track
+    }
+
+    /// Number of nanoseconds per timestamp unit of the file.
+    pub fn timestamp_scale(&self) -> u64 {
+        self.timestamp_scale
+    }
+
+    /// Reads the next frame of the AV1 track.
+    ///
+    /// Returns `Ok(None)` at the end of the file.
+    pub fn read_packet(&mut self) -> io::Result<Option<Packet>> {
+        loop {
+            let header = match self.pending.take() {
+                Some(header) => header,
+                None => match read_element_header(&mut self.reader)? {
+                    Some(header) => header,
+                    None => return Ok(None),
+                },
+            };
+
+            match header.id {
+                // Descend into the cluster
+                CLUSTER => self.cluster_timestamp = 0,
+                TIMESTAMP => {
+                    self.cluster_timestamp =
+                        read_uint(&read_element_data(&mut self.reader, header)?)?;
+                }
+                SIMPLE_BLOCK => {
+                    let data = read_element_data(&mut self.reader, header)?;
+                    if let Some(packet) = self.parse_block(&data, None, None)? {
+                        return Ok(Some(packet));
+                    }
+                }
+                BLOCK_GROUP => {
+                    let data = read_element_data(&mut self.reader, header)?;
+                    let mut block = None;
+                    let mut duration = None;
+                    let mut keyframe = true;
+                    for_each_child(&data, |id, data| {
+                        match id {
+                            BLOCK => block = Some(data),
+                            BLOCK_DURATION => duration = Some(read_uint(data)?),
+                            REFERENCE_BLOCK => keyframe = false,
+                            _ => (),
+                        }
+                        Ok(())
+                    })?;
+
+                    let block = block.ok_or_else(|| invalid_data("Block group without block"))?;
+                    if let Some(packet) = self.parse_block(block, duration, Some(keyframe))? {
+                        return Ok(Some(packet));
+                    }
+                }
+                _ => skip_element(&mut self.reader, header)?,
+            }
+        }
+    }
+
+    fn parse_block(
+        &self,
+        data: &[u8],
+        duration: Option<u64>,
+        keyframe: Option<bool>,
+    ) -> io::Result<Option<Packet>> {
+        let mut buf = data;
+        let (track_number, _) =
+            read_vint(&mut buf, false)?.ok_or_else(|| invalid_data("Truncated block"))?;
+        if track_number != self.track.number {
+            return Ok(None);
+        }
+
+        if buf.len() < 3 {
+            return Err(invalid_data("Truncated block"));
+        }
+        let relative_timestamp = i16::from_be_bytes([buf[0], buf[1]]);
+        let flags = buf[2];
+        if flags & BLOCK_FLAG_LACING != 0 {
+            return Err(invalid_data("Laced AV1 blocks are not supported"));
+        }
+
+        // Both the scale and the cluster timestamp come from the file and can be arbitrarily large
+        let timestamp = i64::try_from(self.cluster_timestamp)
+            .ok()
+            .and_then(|t| t.checked_add(i64::from(relative_timestamp)))
+            .zip(i64::try_from(self.timestamp_scale).ok())
+            .and_then(|(t, scale)| t.checked_mul(scale))
+            .ok_or_else(|| invalid_data("Block timestamp out of range"))?;
+        let duration = duration
+            .map(|d| {
+                d.checked_mul(self.timestamp_scale)
+                    .ok_or_else(|| invalid_data("Block duration out of range"))
+            })
+            .transpose()?;
+
+        Ok(Some(Packet {
+            data: buf[3..].into(),
+            timestamp,
+            duration,
+            keyframe: keyframe.unwrap_or(flags & BLOCK_FLAG_KEYFRAME != 0),
+        }))
+    }
+
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: Read> Iterator for Reader<R> {
+    type Item = io::Result<Packet>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_packet().transpose()
+    }
+}
+
+/// Returns the first AV1 track of a `Tracks` element.
+fn parse_tracks(buf: &[u8]) -> io::Result<Option<Track>> {
+    let mut track = None;
+
+    for_each_child(buf, |id, data| {
+        if id != TRACK_ENTRY || track.is_some() {
+            return Ok(());
+        }
+
+        let mut number = None;
+        let mut codec_id = None;
+        let mut codec_private = None;
+        let mut width = None;
+        let mut height = None;
+        for_each_child(data, |id, data| {
+            match id {
+                TRACK_NUMBER => number = Some(read_uint(data)?),
+                CODEC_ID => codec_id = Some(read_string(data)),
+                CODEC_PRIVATE => codec_private = Some(data),
+                VIDEO => for_each_child(data, |id, data| {
+                    match id {
+                        PIXEL_WIDTH => width = Some(read_uint(data)?),
+                        PIXEL_HEIGHT => height = Some(read_uint(data)?),
+                        _ => (),
+                    }
+                    Ok(())
+                })?,
+                _ => (),
+            }
+            Ok(())
+        })?;
+
+        if codec_id.as_deref() != Some(AV1_CODEC_ID) {
+            return Ok(());
+        }
+
+        track = Some(Track {
+            number: number.ok_or_else(|| invalid_data("Track without number"))?,
+            width,
+            height,
+            codec_configuration: codec_private
+                .map(Av1CodecConfiguration::parse)
+                .transpose()?,
+        });
+
+        Ok(())
+    })?;
+
+    Ok(track)
+}
+
+/// Calls `f` with the ID and data of every child element of a master element.
+fn for_each_child<'a>(
+    mut buf: &'a [u8],
+    mut f: impl FnMut(u32, &'a [u8]) -> io::Result<()>,
+) -> io::Result<()> {
+    while !buf.is_empty() {
+        let header = read_element_header(&mut buf)?
+            .ok_or_else(|| invalid_data("Truncated element header"))?;
+        let size = header
+            .size
+            .filter(|&size| size <= buf.len() as u64)
+            .ok_or_else(|| invalid_data("Invalid element size"))?;
+
+        let (data, rest) = buf.split_at(size as usize);
+        f(header.id, data)?;
+        buf = rest;
+    }
+
+    Ok(())
+}
+
+/// Reads an element header, or returns `None` at the end of the input.
+fn read_element_header(reader: &mut impl Read) -> io::Result<Option<ElementHeader>> {
+    let (id, id_len) = match read_vint(reader, true)? {
+        Some(v) => v,
+        None => return Ok(None),
+    };
+    if id_len > 4 {
+        return Err(invalid_data("Invalid element ID"));
+    }
+
+    let (size, size_len) = read_vint(reader, false)?
+        .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "Truncated element header"))?;
+    // All ones signals an unknown size
+    let unknown = (1 << (7 * size_len)) - 1;
+
+    Ok(Some(ElementHeader {
+        id: id as u32,
+        size: Some(size).filter(|&size| size != unknown),
+    }))
+}
+
+/// Reads a variable length integer, returning the value and its encoded length, or `None` at the
+/// end of the input.
+fn read_vint(reader: &mut impl Read, keep_marker: bool) -> io::Result<Option<(u64, usize)>> {
+    let mut first = [0u8; 1];
+    if reader.read(&mut first)? == 0 {
+        return Ok(None);
+    }
+
+    let len = first[0].leading_zeros() as usize + 1;
+    if len > 8 {
+        return Err(invalid_data("Invalid variable length integer"));
+    }
+
+    let mut value = if keep_marker {
+        u64::from(first[0])
+    } else {
+        u64::from(first[0]) & (0xff >> len)
+    };
+
+    let mut rest = [0u8; 7];
+    reader.read_exact(&mut rest[..len - 1])?;
+    for &byte in &rest[..len - 1] {
+        value = (value << 8) | u64::from(byte);
+    }
+
+    Ok(Some((value, len)))
+}
+
+fn read_element_data(reader: &mut impl Read, header: ElementHeader) -> io::Result<Vec<u8>> {
+    let size = header
+        .size
+        .ok_or_else(|| invalid_data(format!("Element {:#x} of unknown size", header.id)))?;
+
+    // Don't trust the size for allocating everything upfront
+    let mut data = Vec::new();
+    let read = reader.take(size).read_to_end(&mut data)?;
+    if (read as u64) < size {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "Truncated element",
+        ));
+    }
+
+    Ok(data)
+}
+
+fn skip_element(reader: &mut impl Read, header: ElementHeader) -> io::Result<()> {
+    let size = header
+        .size
+        .ok_or_else(|| invalid_data(format!("Element {:#x} of unknown size", header.id)))?;
+
+    let skipped = io::copy(&mut reader.take(size), &mut io::sink())?;
+    if skipped < size {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "Truncated element",
+        ));
+    }
+
+    Ok(())
+}
+
+fn read_uint(data: &[u8]) -> io::Result<u64> {
+    if data.len() > 8 {
+        return Err(invalid_data("Invalid unsigned integer element"));
+    }
+
+    Ok(data.iter().fold(0, |v, &b| (v << 8) | u64::from(b)))
+}
+
+fn read_string(data: &[u8]) -> String {
+    let end = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+    String::from_utf8_lossy(&data[..end]).into_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ivf;
+
+    static TEST_FILE_420_8: &[u8] = include_bytes!("../test-420-8.ivf");
+
+    const VOID: u32 = 0xec;
+    const TRACK_TYPE: u32 = 0x83;
+    const UNKNOWN_SIZE: [u8; 8] = [0x01, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff];
+
+    fn element_id(id: u32) -> Vec<u8> {
+        let bytes = id.to_be_bytes();
+        let start = bytes.iter().position(|&b| b != 0).unwrap();
+        bytes[start..].to_vec()
+    }
+
+    fn element(id: u32, data: &[u8]) -> Vec<u8> {
+        let mut out = element_id(id);
+        // Always use 8 byte sizes
+        out.push(0x01);
+        out.extend_from_slice(&(data.len() as u64).to_be_bytes()[1..]);
+        out.extend_from_slice(data);
+        out
+    }
+
+    fn uint(id: u32, value: u64) -> Vec<u8> {
+        element(id, &value.to_be_bytes())
+    }
+
+    fn block(track: u8, timestamp: i16, flags: u8, data: &[u8]) -> Vec<u8> {
+        let mut out = vec![0x80 | track];
+        out.extend_from_slice(&timestamp.to_be_bytes());
+        out.push(flags);
+        out.extend_from_slice(data);
+        out
+    }
+
+    // Muxes the test file into a WebM file with an additional non-AV1 track and two clusters,
+    // using all supported block types.
+    fn mux_test_file() -> Vec<u8> {
+        let packets = ivf::Reader::new(TEST_FILE_420_8)
+            .unwrap()
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+
+        let mut file = element(EBML, &element(DOC_TYPE, b"webm"));
+
+        let mut segment = element_id(SEGMENT);
+        segment.extend_from_slice(&UNKNOWN_SIZE);
+        segment.extend(element(VOID, &[0; 16]));
+        segment.extend(element(INFO, &uint(TIMESTAMP_SCALE, 1_000_000)));
+
+        let audio_track = [uint(TRACK_NUMBER, 1), element(CODEC_ID, b"A_OPUS")].concat();
+        let video_track = [
+            uint(TRACK_NUMBER, 2),
+            uint(TRACK_TYPE, 1),
+            element(CODEC_ID, b"V_AV1\0"),
+            element(CODEC_PRIVATE, &[0x81, 0x00, 0x0c, 0x00]),
+            element(
+                VIDEO,
+                &[uint(PIXEL_WIDTH, 320), uint(PIXEL_HEIGHT, 240)].concat(),
+            ),
+        ]
+        .concat();
+        segment.extend(element(
+            TRACKS,
+            &[
+                element(TRACK_ENTRY, &audio_track),
+                element(TRACK_ENTRY, &video_track),
+            ]
+            .concat(),
+        ));
+        file.extend(segment);
+
+        // First cluster of unknown size with simple blocks
+        file.extend(element_id(CLUSTER));
+        file.extend_from_slice(&UNKNOWN_SIZE);
+        file.extend(uint(TIMESTAMP, 0));
+        for (i, packet) in packets[..3].iter().enumerate() {
+            let flags = if i == 0 { BLOCK_FLAG_KEYFRAME } else { 0 };
+            file.extend(element(
+                SIMPLE_BLOCK,
+                &block(2, packet.pts as i16, flags, &packet.data),
+            ));
+            file.extend(element(SIMPLE_BLOCK, &block(1, 0, 0, &[0xaa])));
+        }
+
+        // Second cluster with block groups
+        let mut cluster = uint(TIMESTAMP, 100);
+        for packet in &packets[3..] {
+            cluster.extend(element(
+                BLOCK_GROUP,
+                &[
+                    element(BLOCK, &block(2, packet.pts as i16 - 100, 0, &packet.data)),
+                    uint(BLOCK_DURATION, 33),
+                    element(REFERENCE_BLOCK, &[0xdf]),
+                ]
+                .concat(),
+            ));
+        }
+        file.extend(element(CLUSTER, &cluster));
+
+        file
+    }
+
+    #[test]
+    fn test_read() {
+        let file = mux_test_file();
+        let mut r = Reader::new(&file[..]).unwrap();
+        assert_eq!(
+            r.track(),
+            &Track {
+                number: 2,
+                width: Some(320),
+                height: Some(240),
+                codec_configuration: Some(
+                    Av1CodecConfiguration::parse(&[0x81, 0x00, 0x0c, 0x00]).unwrap()
+                ),
+            }
+        );
+        assert_eq!(r.timestamp_scale(), 1_000_000);
+
+        let packets = (&mut r).collect::<io::Result<Vec<_>>>().unwrap();
+        let ivf_packets = ivf::Reader::new(TEST_FILE_420_8)
+            .unwrap()
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(packets.len(), ivf_packets.len());
+
+        for (i, (packet, ivf_packet)) in packets.iter().zip(&ivf_packets).enumerate() {
+            assert_eq!(packet.data, ivf_packet.data);
+            assert_eq!(packet.timestamp, ivf_packet.pts as i64 * 1_000_000);
+            assert_eq!(packet.keyframe, i == 0);
+            assert_eq!(packet.duration, (i >= 3).then_some(33_000_000));
+        }
+    }
+
+    #[test]
+    fn test_invalid() {
+        let file = mux_test_file();
+
+        // Not an EBML file
+        let err = Reader::new(TEST_FILE_420_8).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // Wrong document type
+        let mut broken = file.clone();
+        let pos = broken.windows(4).position(|w| w == b"webm").unwrap();
+        broken[pos..pos + 4].copy_from_slice(b"mkv\0");
+        let err = Reader::new(&broken[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // No AV1 track
+        let mut broken = file.clone();
+        let pos = broken.windows(5).position(|w| w == b"V_AV1").unwrap();
+        broken[pos..pos + 5].copy_from_slice(b"V_VP9");
+        let err = Reader::new(&broken[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // Truncated in the middle of a block
+        let mut r = Reader::new(&file[..file.len() - 10]).unwrap();
+        assert_eq!(r.by_ref().take_while(Result::is_ok).count(), 4);
+    }
+
+    #[test]
+    fn test_timestamp_overflow() {
+        let file = mux_test_file();
+        let scale = uint(TIMESTAMP_SCALE, 1_000_000);
+        let pos = file.windows(scale.len()).position(|w| w == scale).unwrap();
+
+        // Scale not representable as signed timestamp
+        let mut broken = file.clone();
+        broken[pos..pos + scale.len()].copy_from_slice(&uint(TIMESTAMP_SCALE, u64::MAX));
+        let mut r = Reader::new(&broken[..]).unwrap();
+        let err = r.read_packet().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // Only the first packet with timestamp 0 fits
+        let mut broken = file;
+        broken[pos..pos + scale.len()].copy_from_slice(&uint(TIMESTAMP_SCALE, i64::MAX as u64 / 2));
+        let mut r = Reader::new(&broken[..]).unwrap();
+        assert_eq!(r.read_packet().unwrap().unwrap().timestamp, 0);
+        let err = r.read_packet().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,962 @@
+//! Demuxing of AV1 tracks from MP4 files.
+//!
+//! Both regular and fragmented MP4 files are supported. The sample tables of the first track with
+//! an `av01` sample entry are read when opening the file, the samples themselves are read on
+//! demand.
+
+use crate::av1c::Av1CodecConfiguration;
+use crate::bmff::{
+    find_box, for_each_box, invalid_data, read_box_data, read_box_header, ByteReader,
+};
+use std::io::{self, Read, Seek, SeekFrom};
+
+// Sample flags of fragments
+const SAMPLE_IS_NON_SYNC_SAMPLE: u32 = 0x1_0000;
+
+// tfhd flags
+const TFHD_BASE_DATA_OFFSET: u32 = 0x1;
+const TFHD_SAMPLE_DESCRIPTION_INDEX: u32 = 0x2;
+const TFHD_DEFAULT_SAMPLE_DURATION: u32 = 0x8;
+const TFHD_DEFAULT_SAMPLE_SIZE: u32 = 0x10;
+const TFHD_DEFAULT_SAMPLE_FLAGS: u32 = 0x20;
+
+// trun flags
+const TRUN_DATA_OFFSET: u32 = 0x1;
+const TRUN_FIRST_SAMPLE_FLAGS: u32 = 0x4;
+const TRUN_SAMPLE_DURATION: u32 = 0x100;
+const TRUN_SAMPLE_SIZE: u32 = 0x200;
+const TRUN_SAMPLE_FLAGS: u32 = 0x400;
+const TRUN_SAMPLE_COMPOSITION_TIME_OFFSET: u32 = 0x800;
+
+/// AV1 track of an MP4 file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Track {
+    /// Track ID.
+    pub id: u32,
+    /// Number of time units per second of all timestamps of the track.
+    pub timescale: u32,
+    /// Width of the sample entry in pixels.
+    pub width: u16,
+    /// Height of the sample entry in pixels.
+    pub height: u16,
+    /// Codec configuration from the `av1C` box.
+    pub codec_configuration: Av1CodecConfiguration,
+}
+
+/// A sample of the AV1 track.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sample {
+    /// Sample data.
+    pub data: Box<[u8]>,
+    /// Decode timestamp in units of the track timescale.
+    pub dts: i64,
+    /// Presentation timestamp in units of the track timescale.
+    pub pts: i64,
+    /// Duration in units of the track timescale.
+    pub duration: u32,
+    /// Whether the sample is a sync sample, i.e. a keyframe.
+    pub sync: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SampleInfo {
+    offset: u64,
+    size: u32,
+    dts: i64,
+    pts: i64,
+    duration: u32,
+    sync: bool,
+}
+
+/// Defaults for the samples of fragments from the `trex` box.
+#[derive(Debug, Clone, Copy, Default)]
+struct TrackExtends {
+    sample_duration: u32,
+    sample_size: u32,
+    sample_flags: u32,
+}
+
+/// MP4 file reader.
+#[derive(Debug)]
+pub struct Reader<R: Read + Seek> {
+    reader: R,
+    track: Track,
+    samples: Vec<SampleInfo>,
+    next: usize,
+}
+
+impl<R: Read + Seek> Reader<R> {
+    /// Creates a new [`Reader`] and reads the sample tables of the first AV1 track, including the
+    /// ones of all movie fragments.
+    ///
+    /// Fails if the file contains no AV1 track.
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        // Upper bound for the number of samples, as the sample counts of the file can't be trusted
+        let file_start = reader.stream_position()?;
+        let file_len = reader.seek(SeekFrom::End(0))?.saturating_sub(file_start);
+        reader.seek(SeekFrom::Start(file_start))?;
+
+        let mut track = None;
+        let mut samples = Vec::new();
+        let mut trex = TrackExtends::default();
+
+        loop {
+            let start = reader.stream_position()?;
+            let header = match read_box_header(&mut reader)? {
+                Some(header) => header,
+                None => break,
+            };
+
+            match &header.box_type {
+                b"moov" if track.is_none() => {
+                    let moov = read_box_data(&mut reader, header)?;
+                    if let Some((t, s, x)) = parse_moov(&moov, file_len)? {
+                        track = Some(t);
+                        samples = s;
+                        trex = x;
+                    }
+                }
+                b"moof" => {
+                    let moof = read_box_data(&mut reader, header)?;
+                    if let Some(track) = &track {
+                        parse_moof(&moof, start, track.id, &trex, file_len, &mut samples)?;
+                    }
+                }
+                _ => match header.size {
+                    Some(size) => {
+                        let size =
+                            i64::try_from(size).map_err(|_| invalid_data("Invalid box size"))?;
+                        reader.seek(SeekFrom::Current(size))?;
+                    }
+                    None => break,
+                },
+            }
+        }
+
+        let track = track.ok_or_else(|| invalid_data("No AV1 track found"))?;
+
+        Ok(Reader {
+            reader,
+            track,
+            samples,
+            next: 0,
+        })
+    }
+
+    /// The AV1 track that is read.
+    pub fn track(&self) -> &Track {
+        &self.track
+    }
+
+    /// Number of samples of the track.
+    pub fn sample_count(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Reads the next sample in decoding order.
+    ///
+    /// Returns `Ok(None)` after the last sample.
+    pub fn read_sample(&mut self) -> io::Result<Option<Sample>> {
+        let info = match self.samples.get(self.next) {
+            Some(info) => *info,
+            None => return Ok(None),
+        };
+
+        self.reader.seek(SeekFrom::Start(info.offset))?;
+        let mut data = Vec::new();
+        let read = (&mut self.reader)
+            .take(u64::from(info.size))
+            .read_to_end(&mut data)?;
+        if read < info.size as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Truncated sample",
+            ));
+        }
+
+        self.next += 1;
+
+        Ok(Some(Sample {
+            data: data.into_boxed_slice(),
+            dts: info.dts,
+            pts: info.pts,
+            duration: info.duration,
+            sync: info.sync,
+        }))
+    }
+
+    /// Continues reading at the last sync sample with a presentation timestamp not after `pts`,
+    /// or the first sample if there is none.
+    ///
+    /// Returns the presentation timestamp of that sample.
+    pub fn seek(&mut self, pts: i64) -> Option<i64> {
+        let index = self
+            .samples
+            .iter()
+            .rposition(|s| s.sync && s.pts <= pts)
+            .unwrap_or(0);
+        self.next = index;
+
+        self.samples.get(index).map(|s| s.pts)
+    }
+
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: Read + Seek> Iterator for Reader<R> {
+    type Item = io::Result<Sample>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_sample().transpose()
+    }
+}
+
+/// Returns the first AV1 track of a `moov` box with its samples and fragment defaults.
+fn parse_moov(
+    moov: &[u8],
+    file_len: u64,
+) -> io::Result<Option<(Track, Vec<SampleInfo>, TrackExtends)>> {
+    let mut result = None;
+    for_each_box(moov, |box_type, trak| {
+        if &box_type != b"trak" || result.is_some() {
+            return Ok(());
+        }
+        result = parse_trak(trak, file_len)?;
+        Ok(())
+    })?;
+
+    let (track, samples) = match result {
+        Some(result) => result,
+        None => return Ok(None),
+    };
+
+    let mut trex = TrackExtends::default();
+    if let Some(mvex) = find_box(moov, b"mvex")? {
+        for_each_box(mvex, |box_type, data| {
+            if &box_type != b"trex" {
+                return Ok(());
+            }
+
+            let mut r = ByteReader::new(data);
+            r.full_box_header()?;
+            if r.u32()? == track.id {
+                r.skip(4)?;
+                trex = TrackExtends {
+                    sample_duration: r.u32()?,
+                    sample_size: r.u32()?,
+                    sample_flags: r.u32()?,
+                };
+            }
+            Ok(())
+        })?;
+    }
+
+    Ok(Some((track, samples, trex)))
+}
+
+fn parse_trak(trak: &[u8], file_len: u64) -> io::Result<Option<(Track, Vec<SampleInfo>)>> {
+    let mdia = find_box(trak, b"mdia")?;
+    let stbl = match mdia
+        .map(|mdia| find_box(mdia, b"minf"))
+        .transpose()?
+        .flatten()
+    {
+        Some(minf) => find_box(minf, b"stbl")?,
+        None => None,
+    };
+    let (mdia, stbl) = match (mdia, stbl) {
+        (Some(mdia), Some(stbl)) => (mdia, stbl),
+        _ => return Ok(None),
+    };
+
+    let (width, height, codec_configuration) = match parse_stsd(stbl)? {
+        Some(entry) => entry,
+        None => return Ok(None),
+    };
+
+    let tkhd = find_box(trak, b"tkhd")?.ok_or_else(|| invalid_data("Track without tkhd"))?;
+    let mut r = ByteReader::new(tkhd);
+    let (version, _) = r.full_box_header()?;
+    r.skip(if version == 1 { 16 } else { 8 })?;
+    let id = r.u32()?;
+
+    let mdhd = find_box(mdia, b"mdhd")?.ok_or_else(|| invalid_data("Track without mdhd"))?;
+    let mut r = ByteReader::new(mdhd);
+    let (version, _) = r.full_box_header()?;
+    r.skip(if version == 1 { 16 } else { 8 })?;
+    let timescale = r.u32()?;
+
+    let samples = parse_stbl(stbl, file_len)?;
+
+    Ok(Some((
+        Track {
+            id,
+            timescale,
+            width,
+            height,
+            codec_configuration,
+        },
+        samples,
+    )))
+}
+
+/// Parses the first `av01` sample entry of a sample table.
+fn parse_stsd(stbl: &[u8]) -> io::Result<Option<(u16, u16, Av1CodecConfiguration)>> {
+    let stsd = match find_box(stbl, b"stsd")? {
+        Some(stsd) => stsd,
+        None => return Ok(None),
+    };
+
+    let mut r = ByteReader::new(stsd);
+    r.full_box_header()?;
+    r.skip(4)?;
+
+    let av01 = match find_box(r.rest(), b"av01")? {
+        Some(av01) => av01,
+        None => return Ok(None),
+    };
+
+    // Skip the SampleEntry and VisualSampleEntry fields before the size and after it
+    let mut r = ByteReader::new(av01);
+    r.skip(24)?;
+    let width = r.u16()?;
+    let height = r.u16()?;
+    r.skip(50)?;
+
+    let av1c = find_box(r.rest(), b"av1C")?.ok_or_else(|| invalid_data("av01 without av1C"))?;
+
+    Ok(Some((width, height, Av1CodecConfiguration::parse(av1c)?)))
+}
+
+/// Checks that `count` samples of `size` bytes each, or at least one byte for empty samples, fit
+/// into a file of `file_len` bytes.
+///
+/// This prevents huge allocations for sample counts that are not backed by per-sample data in the
+/// file.
+fn check_sample_count(count: u64, size: u32, file_len: u64) -> io::Result<()> {
+    if count.saturating_mul(u64::from(size.max(1))) > file_len {
+        return Err(invalid_data("Sample count exceeds the file size"));
+    }
+
+    Ok(())
+}
+
+fn parse_stbl(stbl: &[u8], file_len: u64) -> io::Result<Vec<SampleInfo>> {
+    let mut stts = None;
+    let mut ctts = None;
+    let mut stsc = None;
+    let mut stsz = None;
+    let mut stco = None;
+    let mut co64 = None;
+    let mut stss = None;
+    for_each_box(stbl, |box_type, data| {
+        match &box_type {
+            b"stts" => stts = Some(data),
+            b"ctts" => ctts = Some(data),
+            b"stsc" => stsc = Some(data),
+            b"stsz" => stsz = Some(data),
+            b"stco" => stco = Some(data),
+            b"co64" => co64 = Some(data),
+            b"stss" => stss = Some(data),
+            _ => (),
+        }
+        Ok(())
+    })?;
+
+    // Sample sizes
+    let sizes = match stsz {
+        Some(stsz) => {
+            let mut r = ByteReader::new(stsz);
+            r.full_box_header()?;
+            let sample_size = r.u32()?;
+            let count = r.u32()?;
+            if sample_size != 0 {
+                check_sample_count(u64::from(count), sample_size, file_len)?;
+                vec![sample_size; count as usize]
+            } else {
+                if count as usize > r.rest().len() / 4 {
+                    return Err(invalid_data("Truncated sample size box"));
+                }
+                (0..count)
+                    .map(|_| r.u32())
+                    .collect::<io::Result<Vec<_>>>()?
+            }
+        }
+        // Only fragmented files have no sample size box
+        None => return Ok(Vec::new()),
+    };
+    let mut samples = sizes
+        .iter()
+        .map(|&size| SampleInfo {
+            offset: 0,
+            size,
+            dts: 0,
+            pts: 0,
+            duration: 0,
+            sync: stss.is_none(),
+        })
+        .collect::<Vec<_>>();
+
+    // Decode timestamps
+    if let Some(stts) = stts {
+        let mut r = ByteReader::new(stts);
+        r.full_box_header()?;
+        let mut samples = samples.iter_mut();
+        let mut dts = 0i64;
+        for _ in 0..r.u32()? {
+            let count = r.u32()?;
+            let delta = r.u32()?;
+            for sample in samples.by_ref().take(count as usize) {
+                sample.dts = dts;
+                sample.duration = delta;
+                dts = dts
+                    .checked_add(i64::from(delta))
+                    .ok_or_else(|| invalid_data("Decode timestamp out of range"))?;
+            }
+        }
+    }
+    for sample in &mut samples {
+        sample.pts = sample.dts;
+    }
+
+    // Composition offsets
+    if let Some(ctts) = ctts {
+        let mut r = ByteReader::new(ctts);
+        r.full_box_header()?;
+        let mut samples = samples.iter_mut();
+        for _ in 0..r.u32()? {
+            let count = r.u32()?;
+            // Version 0 offsets are unsigned but commonly written as signed values
+            let offset = r.u32()? as i32;
+            for sample in samples.by_ref().take(count as usize) {
+                sample.pts = sample
+                    .dts
+                    .checked_add(i64::from(offset))
+                    .ok_or_else(|| invalid_data("Presentation timestamp out of range"))?;
+            }
+        }
+    }
+
+    // Sync samples
+    if let Some(stss) = stss {
+        let mut r = ByteReader::new(stss);
+        r.full_box_header()?;
+        for _ in 0..r.u32()? {
+            let index = r.u32()? as usize;
+            if let Some(sample) = index.checked_sub(1).and_then(|i| samples.get_mut(i)) {
+                sample.sync = true;
+            }
+        }
+    }
+
+    // Chunk offsets
+    let chunk_offsets = match (stco, co64) {
+        (Some(stco), _) => {
+            let mut r = ByteReader::new(stco);
+            r.full_box_header()?;
+            (0..r.u32()?)
+                .map(|_| r.u32().map(u64::from))
+                .collect::<io::Result<Vec<_>>>()?
+        }
+        (None, Some(co64)) => {
+            let mut r = ByteReader::new(co64);
+            r.full_box_header()?;
+            (0..r.u32()?)
+                .map(|_| r.u64())
+                .collect::<io::Result<Vec<_>>>()?
+        }
+        (None, None) => return Err(invalid_data("Sample table without chunk offsets")),
+    };
+
+    // Sample to chunk mapping
+    let stsc = stsc.ok_or_else(|| invalid_data("Sample table without stsc"))?;
+    let mut r = ByteReader::new(stsc);
+    r.full_box_header()?;
+    let entries = (0..r.u32()?)
+        .map(|_| {
+            let first_chunk = r.u32()?;
+            let samples_per_chunk = r.u32()?;
+            r.skip(4)?;
+            Ok((first_chunk, samples_per_chunk))
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+
+    let mut samples_iter = samples.iter_mut();
+    for (i, &(first_chunk, samples_per_chunk)) in entries.iter().enumerate() {
+        let first_chunk = first_chunk.max(1) as usize - 1;
+        let last_chunk = entries
+            .get(i + 1)
+            .map(|&(next, _)| next.max(1) as usize - 1)
+            .unwrap_or(chunk_offsets.len())
+            .min(chunk_offsets.len());
+
+        for &chunk_offset in chunk_offsets.get(first_chunk..last_chunk).unwrap_or(&[]) {
+            let mut offset = chunk_offset;
+            for sample in samples_iter.by_ref().take(samples_per_chunk as usize) {
+                sample.offset = offset;
+                offset = offset
+                    .checked_add(u64::from(sample.size))
+                    .ok_or_else(|| invalid_data("Sample offset out of range"))?;
+            }
+        }
+    }
+
+    Ok(samples)
+}
+
+/// Appends the samples of the track with ID `track_id` of a `moof` box starting at file offset
+/// `moof_offset`.
+fn parse_moof(
+    moof: &[u8],
+    moof_offset: u64,
+    track_id: u32,
+    trex: &TrackExtends,
+    file_len: u64,
+    samples: &mut Vec<SampleInfo>,
+) -> io::Result<()> {
+    for_each_box(moof, |box_type, traf| {
+        if &box_type != b"traf" {
+            return Ok(());
+        }
+
+        let tfhd = find_box(traf, b"tfhd")?.ok_or_else(|| invalid_data("traf without tfhd"))?;
+        let mut r = ByteReader::new(tfhd);
+        let (_, flags) = r.full_box_header()?;
+        if r.u32()? != track_id {
+            return Ok(());
+        }
+
+        // Without explicit base data offset the data offsets are relative to the `moof`. This is
+        // not correct for files with multiple `traf` of the same track in a `moof` without
+        // `default-base-is-moof` but these don't exist in practice.
+        let mut base_data_offset = moof_offset;
+        let mut defaults = *trex;
+        if flags & TFHD_BASE_DATA_OFFSET != 0 {
+            base_data_offset = r.u64()?;
+        }
+        if flags & TFHD_SAMPLE_DESCRIPTION_INDEX != 0 {
+            r.skip(4)?;
+        }
+        if flags & TFHD_DEFAULT_SAMPLE_DURATION != 0 {
+            defaults.sample_duration = r.u32()?;
+        }
+        if flags & TFHD_DEFAULT_SAMPLE_SIZE != 0 {
+            defaults.sample_size = r.u32()?;
+        }
+        if flags & TFHD_DEFAULT_SAMPLE_FLAGS != 0 {
+            defaults.sample_flags = r.u32()?;
+        }
+
+        let mut dts = match find_box(traf, b"tfdt")? {
+            Some(tfdt) => {
+                let mut r = ByteReader::new(tfdt);
+                let (version, _) = r.full_box_header()?;
+                if version == 1 {
+                    r.u64()? as i64
+                } else {
+                    i64::from(r.u32()?)
+                }
+            }
+            None => match samples.last() {
+                Some(s) => s
+                    .dts
+                    .checked_add(i64::from(s.duration))
+                    .ok_or_else(|| invalid_data("Decode timestamp out of range"))?,
+                None => 0,
+            },
+        };
+
+        let mut data_offset = base_data_offset;
+        for_each_box(traf, |box_type, trun| {
+            if &box_type != b"trun" {
+                return Ok(());
+            }
+
+            let mut r = ByteReader::new(trun);
+            let (_, flags) = r.full_box_header()?;
+            let count = r.u32()?;
+            if flags & TRUN_DATA_OFFSET != 0 {
+                data_offset = base_data_offset.wrapping_add(r.u32()? as i32 as u64);
+            }
+            let first_sample_flags = if flags & TRUN_FIRST_SAMPLE_FLAGS != 0 {
+                Some(r.u32()?)
+            } else {
+                None
+            };
+
+            let sample_len = [
+                TRUN_SAMPLE_DURATION,
+                TRUN_SAMPLE_SIZE,
+                TRUN_SAMPLE_FLAGS,
+                TRUN_SAMPLE_COMPOSITION_TIME_OFFSET,
+            ]
+            .iter()
+            .filter(|&&f| flags & f != 0)
+            .count()
+                * 4;
+            if sample_len == 0 {
+                // All samples of the track so far must fit into the file
+                check_sample_count(
+                    samples.len() as u64 + u64::from(count),
+                    defaults.sample_size,
+                    file_len,
+                )?;
+            } else if count as usize > r.rest().len() / sample_len {
+                return Err(invalid_data("Truncated track run box"));
+            }
+
+            for i in 0..count {
+                let duration = if flags & TRUN_SAMPLE_DURATION != 0 {
+                    r.u32()?
+                } else {
+                    defaults.sample_duration
+                };
+                let size = if flags & TRUN_SAMPLE_SIZE != 0 {
+                    r.u32()?
+                } else {
+                    defaults.sample_size
+                };
+                let sample_flags = if flags & TRUN_SAMPLE_FLAGS != 0 {
+                    r.u32()?
+                } else {
+                    defaults.sample_flags
+                };
+                let sample_flags = match first_sample_flags {
+                    Some(first_sample_flags) if i == 0 => first_sample_flags,
+                    _ => sample_flags,
+                };
+                // Version 0 offsets are unsigned but commonly written as signed values
+                let composition_offset = if flags & TRUN_SAMPLE_COMPOSITION_TIME_OFFSET != 0 {
+                    r.u32()? as i32
+                } else {
+                    0
+                };
+
+                samples.push(SampleInfo {
+                    offset: data_offset,
+                    size,
+                    dts,
+                    pts: dts
+                        .checked_add(i64::from(composition_offset))
+                        .ok_or_else(|| invalid_data("Presentation timestamp out of range"))?,
+                    duration,
+                    sync: sample_flags & SAMPLE_IS_NON_SYNC_SAMPLE == 0,
+                });
+
+                data_offset = data_offset
+                    .checked_add(u64::from(size))
+                    .ok_or_else(|| invalid_data("Sample offset out of range"))?;
+                dts = dts
+                    .checked_add(i64::from(duration))
+                    .ok_or_else(|| invalid_data("Decode timestamp out of range"))?;
+            }
+
+            Ok(())
+        })?;
+
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bmff::test::{full_box, mp4_box, u32s};
+    use crate::ivf;
+
+    static TEST_FILE_420_8: &[u8] = include_bytes!("../test-420-8.ivf");
+
+    const AV1C: [u8; 4] = [0x81, 0x00, 0x0c, 0x00];
+
+    fn packets() -> Vec<ivf::Packet> {
+        ivf::Reader::new(TEST_FILE_420_8)
+            .unwrap()
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap()
+    }
+
+    fn trak(stbl_boxes: &[u8]) -> Vec<u8> {
+        let mut av01 = vec![0; 24];
+        av01.extend_from_slice(&320u16.to_be_bytes());
+        av01.extend_from_slice(&240u16.to_be_bytes());
+        av01.extend_from_slice(&[0; 50]);
+        av01.extend(mp4_box(b"av1C", &AV1C));
+
+        let stsd = full_box(
+            b"stsd",
+            0,
+            0,
+            &[u32s(&[1]), mp4_box(b"av01", &av01)].concat(),
+        );
+        let stbl = mp4_box(b"stbl", &[stsd, stbl_boxes.to_vec()].concat());
+        let minf = mp4_box(b"minf", &stbl);
+        let mdhd = full_box(b"mdhd", 0, 0, &u32s(&[0, 0, 1000, 0, 0]));
+        let mdia = mp4_box(b"mdia", &[mdhd, minf].concat());
+        let tkhd = full_box(b"tkhd", 0, 0, &u32s(&[0, 0, 7, 0, 0]));
+
+        mp4_box(b"trak", &[tkhd, mdia].concat())
+    }
+
+    // Muxes the test file with one chunk per two samples and the sync samples signalled, using
+    // 64 bit chunk offsets if `co64` is set.
+    fn mux_test_file(co64: bool) -> Vec<u8> {
+        let packets = packets();
+        let ftyp = mp4_box(b"ftyp", b"isom\0\0\0\0isomav01");
+
+        let mdat_data = packets
+            .iter()
+            .flat_map(|p| p.data.iter().copied())
+            .collect::<Vec<_>>();
+
+        // Chunk offsets are relative to the start of the mdat content, patched below
+        let mut chunk_offsets = Vec::new();
+        let mut offset = 0;
+        for chunk in packets.chunks(2) {
+            chunk_offsets.push(offset);
+            offset += chunk.iter().map(|p| p.data.len() as u64).sum::<u64>();
+        }
+
+        let stbl_boxes = |mdat_offset: u64| {
+            let stco = if co64 {
+                let offsets = chunk_offsets
+                    .iter()
+                    .flat_map(|o| (o + mdat_offset).to_be_bytes())
+                    .collect::<Vec<_>>();
+                full_box(b"co64", 0, 0, &[u32s(&[3]), offsets].concat())
+            } else {
+                let offsets = chunk_offsets
+                    .iter()
+                    .map(|o| (o + mdat_offset) as u32)
+                    .collect::<Vec<_>>();
+                full_box(b"stco", 0, 0, &[u32s(&[3]), u32s(&offsets)].concat())
+            };
+
+            let sizes = packets
+                .iter()
+                .map(|p| p.data.len() as u32)
+                .collect::<Vec<_>>();
+
+            [
+                // Durations of 33, 34, 33, 33, 33 ms
+                full_box(b"stts", 0, 0, &u32s(&[3, 1, 33, 1, 34, 3, 33])),
+                // Presentation delayed by 10 ms for all but the first sample
+                full_box(b"ctts", 0, 0, &u32s(&[2, 1, 0, 4, 10])),
+                full_box(b"stsc", 0, 0, &u32s(&[2, 1, 2, 1, 3, 1, 1])),
+                full_box(b"stsz", 0, 0, &[u32s(&[0, 5]), u32s(&sizes)].concat()),
+                stco,
+                full_box(b"stss", 0, 0, &u32s(&[2, 1, 4])),
+            ]
+            .concat()
+        };
+
+        let moov_len = mp4_box(b"moov", &trak(&stbl_boxes(0))).len();
+        let mdat_offset = (ftyp.len() + moov_len + 8) as u64;
+        let moov = mp4_box(b"moov", &trak(&stbl_boxes(mdat_offset)));
+
+        [ftyp, moov, mp4_box(b"mdat", &mdat_data)].concat()
+    }
+
+    // Muxes the test file as fragmented MP4 with two fragments.
+    fn mux_fragmented_test_file() -> Vec<u8> {
+        let packets = packets();
+        let ftyp = mp4_box(b"ftyp", b"iso6\0\0\0\0iso6av01");
+        let trex = full_box(
+            b"trex",
+            0,
+            0,
+            &u32s(&[7, 1, 33, 0, SAMPLE_IS_NON_SYNC_SAMPLE]),
+        );
+        let moov = mp4_box(b"moov", &[trak(&[]), mp4_box(b"mvex", &trex)].concat());
+
+        let mut file = [ftyp, moov].concat();
+        let mut dts = 0;
+        for (i, fragment) in packets.chunks(3).enumerate() {
+            let sizes = fragment
+                .iter()
+                .map(|p| p.data.len() as u32)
+                .collect::<Vec<_>>();
+
+            let moof = |data_offset: u32| {
+                let mfhd = full_box(b"mfhd", 0, 0, &u32s(&[i as u32 + 1]));
+                // Default base is moof
+                let tfhd = full_box(b"tfhd", 0, 0x2_0000, &u32s(&[7]));
+                let tfdt = full_box(b"tfdt", 1, 0, &(dts as u64).to_be_bytes());
+                let trun = full_box(
+                    b"trun",
+                    0,
+                    TRUN_DATA_OFFSET | TRUN_FIRST_SAMPLE_FLAGS | TRUN_SAMPLE_SIZE,
+                    &[u32s(&[sizes.len() as u32, data_offset, 0]), u32s(&sizes)].concat(),
+                );
+                let traf = mp4_box(b"traf", &[tfhd, tfdt, trun].concat());
+                mp4_box(b"moof", &[mfhd, traf].concat())
+            };
+
+            let moof_len = moof(0).len() as u32;
+            file.extend(moof(moof_len + 8));
+            file.extend(mp4_box(
+                b"mdat",
+                &fragment
+                    .iter()
+                    .flat_map(|p| p.data.iter().copied())
+                    .collect::<Vec<_>>(),
+            ));
+
+            dts += 33 * fragment.len() as i64;
+        }
+
+        file
+    }
+
+    #[test]
+    fn test_read() {
+        for co64 in [false, true] {
+            let file = mux_test_file(co64);
+            let mut r = Reader::new(io::Cursor::new(file)).unwrap();
+            assert_eq!(
+                r.track(),
+                &Track {
+                    id: 7,
+                    timescale: 1000,
+                    width: 320,
+                    height: 240,
+                    codec_configuration: Av1CodecConfiguration::parse(&AV1C).unwrap(),
+                }
+            );
+            assert_eq!(r.sample_count(), 5);
+
+            let samples = (&mut r).collect::<io::Result<Vec<_>>>().unwrap();
+            let packets = packets();
+            assert_eq!(samples.len(), packets.len());
+            for (sample, packet) in samples.iter().zip(&packets) {
+                assert_eq!(sample.data, packet.data);
+            }
+
+            assert_eq!(
+                samples.iter().map(|s| s.dts).collect::<Vec<_>>(),
+                [0, 33, 67, 100, 133]
+            );
+            assert_eq!(
+                samples.iter().map(|s| s.pts).collect::<Vec<_>>(),
+                [0, 43, 77, 110, 143]
+            );
+            assert_eq!(
+                samples.iter().map(|s| s.sync).collect::<Vec<_>>(),
+                [true, false, false, true, false]
+            );
+
+            // Seeking goes to the previous sync sample
+            assert_eq!(r.seek(120), Some(110));
+            assert_eq!(r.read_sample().unwrap().unwrap().dts, 100);
+            assert_eq!(r.seek(50), Some(0));
+            assert_eq!(r.read_sample().unwrap().unwrap().dts, 0);
+        }
+    }
+
+    #[test]
+    fn test_read_fragmented() {
+        let file = mux_fragmented_test_file();
+        let mut r = Reader::new(io::Cursor::new(file)).unwrap();
+        assert_eq!(r.track().id, 7);
+        assert_eq!(r.sample_count(), 5);
+
+        let samples = (&mut r).collect::<io::Result<Vec<_>>>().unwrap();
+        let packets = packets();
+        for (sample, packet) in samples.iter().zip(&packets) {
+            assert_eq!(sample.data, packet.data);
+            assert_eq!(sample.duration, 33);
+        }
+
+        assert_eq!(
+            samples.iter().map(|s| s.pts).collect::<Vec<_>>(),
+            [0, 33, 66, 99, 132]
+        );
+        assert_eq!(
+            samples.iter().map(|s| s.sync).collect::<Vec<_>>(),
+            [true, false, false, true, false]
+        );
+    }
+
+    #[test]
+    fn test_huge_sample_count() {
+        // Constant sample size in the sample size box
+        let mut file = mux_test_file(false);
+        let pos = file.windows(4).position(|w| w == b"stsz").unwrap() + 8;
+        file[pos..pos + 8].copy_from_slice(&u32s(&[1, u32::MAX]));
+        let err = Reader::new(io::Cursor::new(file)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // Individual sample sizes in the sample size box
+        let mut file = mux_test_file(false);
+        let pos = file.windows(4).position(|w| w == b"stsz").unwrap() + 8;
+        file[pos..pos + 8].copy_from_slice(&u32s(&[0, u32::MAX]));
+        let err = Reader::new(io::Cursor::new(file)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // Track run with and without sample sizes
+        for flags in [TRUN_SAMPLE_SIZE, 0] {
+            let mut file = mux_fragmented_test_file();
+            let pos = file.windows(4).position(|w| w == b"trun").unwrap() + 4;
+            file[pos..pos + 8].copy_from_slice(&u32s(&[TRUN_DATA_OFFSET | flags, u32::MAX]));
+            let err = Reader::new(io::Cursor::new(file)).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn test_offset_overflow() {
+        let ftyp = mp4_box(b"ftyp", b"iso6\0\0\0\0iso6av01");
+        let moov = mp4_box(b"moov", &trak(&[]));
+
+        let tfhd = full_box(
+            b"tfhd",
+            0,
+            TFHD_BASE_DATA_OFFSET,
+            &[u32s(&[7]), (u64::MAX - 10).to_be_bytes().to_vec()].concat(),
+        );
+        let trun = full_box(b"trun", 0, TRUN_SAMPLE_SIZE, &u32s(&[2, 64, 89]));
+        let traf = mp4_box(b"traf", &[tfhd, trun].concat());
+        let moof = mp4_box(b"moof", &traf);
+        let file = [ftyp.clone(), moov.clone(), moof].concat();
+        let err = Reader::new(io::Cursor::new(file)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let tfhd = full_box(b"tfhd", 0, 0, &u32s(&[7]));
+        let tfdt = full_box(b"tfdt", 1, 0, &(i64::MAX as u64 - 10).to_be_bytes());
+        let trun = full_box(
+            b"trun",
+            0,
+            TRUN_SAMPLE_DURATION | TRUN_SAMPLE_SIZE,
+            &u32s(&[2, 33, 0, 33, 0]),
+        );
+        let traf = mp4_box(b"traf", &[tfhd, tfdt, trun].concat());
+        let moof = mp4_box(b"moof", &traf);
+        let file = [ftyp, moov, moof].concat();
+        let err = Reader::new(io::Cursor::new(file)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_oversized_box() {
+        let mut file = mux_test_file(false);
+        let mut free = u32s(&[1]);
+        free.extend_from_slice(b"free");
+        free.extend_from_slice(&(u64::MAX - 15).to_be_bytes());
+        file.extend(free);
+
+        let err = Reader::new(io::Cursor::new(file)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_no_av1_track() {
+        let mut file = mux_test_file(false);
+        let pos = file.windows(4).rposition(|w| w == b"av01").unwrap();
+        file[pos..pos + 4].copy_from_slice(b"vp09");
+
+        let err = Reader::new(io::Cursor::new(file)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,499 @@
+//! Inspection of the OBUs of an AV1 bitstream.
+//!
+//! This only parses the OBU headers and sizes, the OBU payloads are not interpreted.
+
+use crate::TryFromEnumError;
+use dav1d_sys::*;
+use std::fmt;
+use std::io::{self, Read};
+use std::ops::Range;
+
+// OBU header fields
+const OBU_FORBIDDEN_BIT: u8 = 1 << 7;
+pub(crate) const OBU_EXTENSION_FLAG: u8 = 1 << 2;
+pub(crate) const OBU_HAS_SIZE_FIELD: u8 = 1 << 1;
+
+// leb128 values are at most 8 bytes long and must fit into 32 bits.
+const LEB128_MAX_LEN: usize = 8;
+
+/// Type of an OBU.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ObuType {
+    /// Sequence header.
+    SequenceHeader,
+    /// Temporal delimiter.
+    TemporalDelimiter,
+    /// Frame header.
+    FrameHeader,
+    /// Tile group.
+    TileGroup,
+    /// Metadata.
+    Metadata,
+    /// Frame header and tile group.
+    Frame,
+    /// Redundant copy of a frame header.
+    RedundantFrameHeader,
+    /// Padding.
+    Padding,
+}
+
+impl TryFrom<u32> for ObuType {
+    type Error = TryFromEnumError;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            DAV1D_OBU_SEQ_HDR => Ok(ObuType::SequenceHeader),
+            DAV1D_OBU_TD => Ok(ObuType::TemporalDelimiter),
+            DAV1D_OBU_FRAME_HDR => Ok(ObuType::FrameHeader),
+            DAV1D_OBU_TILE_GRP => Ok(ObuType::TileGroup),
+            DAV1D_OBU_METADATA => Ok(ObuType::Metadata),
+            DAV1D_OBU_FRAME => Ok(ObuType::Frame),
+            DAV1D_OBU_REDUNDANT_FRAME_HDR => Ok(ObuType::RedundantFrameHeader),
+            DAV1D_OBU_PADDING => Ok(ObuType::Padding),
+            _ => Err(TryFromEnumError(())),
+        }
+    }
+}
+
+impl From<ObuType> for u32 {
+    fn from(v: ObuType) -> u32 {
+        match v {
+            ObuType::SequenceHeader => DAV1D_OBU_SEQ_HDR,
+            ObuType::TemporalDelimiter => DAV1D_OBU_TD,
+            ObuType::FrameHeader => DAV1D_OBU_FRAME_HDR,
+            ObuType::TileGroup => DAV1D_OBU_TILE_GRP,
+            ObuType::Metadata => DAV1D_OBU_METADATA,
+            ObuType::Frame => DAV1D_OBU_FRAME,
+            ObuType::RedundantFrameHeader => DAV1D_OBU_REDUNDANT_FRAME_HDR,
+            ObuType::Padding => DAV1D_OBU_PADDING,
+        }
+    }
+}
+
+/// OBU extension header.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ObuExtension {
+    /// Temporal layer of the OBU.
+    pub temporal_id: u8,
+    /// Spatial layer of the OBU.
+    pub spatial_id: u8,
+}
+
+/// OBU header.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ObuHeader {
+    /// Raw `obu_type`, see [`ObuHeader::obu_type`].
+    pub raw_type: u8,
+    /// Extension header, if present.
+    pub extension: Option<ObuExtension>,
+    /// Whether the OBU has a size field.
+    pub has_size_field: bool,
+}
+
+impl ObuHeader {
+    /// Parses an OBU header, returning it together with its length in bytes.
+    ///
+    /// The length does not include the size field.
+    pub fn parse(buf: &[u8]) -> Result<(ObuHeader, usize), ObuError> {
+        let header = *buf.first().ok_or(ObuError::TruncatedHeader { offset: 0 })?;
+        if header & OBU_FORBIDDEN_BIT != 0 {
+            return Err(ObuError::ForbiddenBit { offset: 0 });
+        }
+
+        let extension = if header & OBU_EXTENSION_FLAG != 0 {
+            let extension = *buf.get(1).ok_or(ObuError::TruncatedHeader { offset: 0 })?;
+            Some(ObuExtension {
+                temporal_id: extension >> 5,
+                spatial_id: (extension >> 3) & 0x3,
+            })
+        } else {
+            None
+        };
+
+        Ok((
+            ObuHeader {
+                raw_type: (header >> 3) & 0xf,
+                extension,
+                has_size_field: header & OBU_HAS_SIZE_FIELD != 0,
+            },
+            if extension.is_some() { 2 } else { 1 },
+        ))
+    }
+
+    /// Type of the OBU or `None` for reserved types.
+    pub fn obu_type(&self) -> Option<ObuType> {
+        ObuType::try_from(u32::from(self.raw_type)).ok()
+    }
+}
+
+/// An OBU of a buffer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Obu<'a> {
+    /// OBU header.
+    pub header: ObuHeader,
+    /// Byte range of the whole OBU, including the header and size field, in the buffer.
+    pub range: Range<usize>,
+    /// Byte range of the OBU payload in the buffer.
+    pub payload_range: Range<usize>,
+    buf: &'a [u8],
+}
+
+impl<'a> Obu<'a> {
+    /// Type of the OBU or `None` for reserved types.
+    pub fn obu_type(&self) -> Option<ObuType> {
+        self.header.obu_type()
+    }
+
+    /// The whole OBU, including the header and size field.
+    pub fn data(&self) -> &'a [u8] {
+        &self.buf[self.range.clone()]
+    }
+
+    /// The OBU payload.
+    pub fn payload(&self) -> &'a [u8] {
+        &self.buf[self.payload_range.clone()]
+    }
+}
+
+/// Iterator over the OBUs of a buffer, created by [`iter`].
+///
+/// Iteration stops after the first error.
+#[derive(Debug, Clone)]
+pub struct Iter<'a> {
+    buf: &'a [u8],
+    pos: usize,
+    failed: bool,
+}
+
+/// Iterates over the OBUs of `buf`, e.g. a temporal unit in low overhead bitstream format.
+///
+/// An OBU without size field extends to the end of the buffer.
+pub fn iter(buf: &[u8]) -> Iter<'_> {
+    Iter {
+        buf,
+        pos: 0,
+        failed: false,
+    }
+}
+
+impl<'a> Iter<'a> {
+    fn parse_obu(&self) -> Result<Obu<'a>, ObuError> {
+        let start = self.pos;
+        let (header, header_len) =
+            ObuHeader::parse(&self.buf[start..]).map_err(|e| e.with_offset(start))?;
+
+        let mut payload_start = start + header_len;
+        let payload_end = if header.has_size_field {
+            let (size, size_len) =
+                leb128(&self.buf[payload_start..]).map_err(|e| e.with_offset(payload_start))?;
+            payload_start += size_len;
+
+            let available = self.buf.len() - payload_start;
+            if size > available as u64 {
+                return Err(ObuError::TruncatedPayload {
+                    offset: start,
+                    size,
+                    available,
+                });
+            }
+            payload_start + size as usize
+        } else {
+            self.buf.len()
+        };
+
+        Ok(Obu {
+            header,
+            range: start..payload_end,
+            payload_range: payload_start..payload_end,
+            buf: self.buf,
+        })
+    }
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = Result<Obu<'a>, ObuError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.pos >= self.buf.len() {
+            return None;
+        }
+
+        match self.parse_obu() {
+            Ok(obu) => {
+                self.pos = obu.range.end;
+                Some(Ok(obu))
+            }
+            Err(err) => {
+                self.failed = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+impl std::iter::FusedIterator for Iter<'_> {}
+
+/// Parses a leb128 value at the start of `buf`, returning the value and its encoded length.
+pub fn leb128(buf: &[u8]) -> Result<(u64, usize), ObuError> {
+    let mut value = 0u64;
+
+    for (i, &byte) in buf.iter().take(LEB128_MAX_LEN).enumerate() {
+        value |= u64::from(byte & 0x7f) << (i * 7);
+        if byte & 0x80 == 0 {
+            if value > u64::from(u32::MAX) {
+                return Err(ObuError::Leb128Overflow { offset: 0 });
+            }
+            return Ok((value, i + 1));
+        }
+    }
+
+    if buf.len() < LEB128_MAX_LEN {
+        Err(ObuError::TruncatedLeb128 { offset: 0 })
+    } else {
+        Err(ObuError::Leb128TooLong { offset: 0 })
+    }
+}
+
+/// Reads a leb128 value, returning the value and its encoded length, or `None` at the end of the
+/// input.
+pub(crate) fn read_leb128(reader: &mut impl Read) -> io::Result<Option<(u64, usize)>> {
+    let mut buf = [0u8; LEB128_MAX_LEN];
+
+    for i in 0..LEB128_MAX_LEN {
+        if reader.read(&mut buf[i..][..1])? == 0 {
+            if i == 0 {
+                return Ok(None);
+            }
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                ObuError::TruncatedLeb128 { offset: 0 },
+            ));
+        }
+
+        if buf[i] & 0x80 == 0 {
+            return leb128(&buf[..=i]).map(Some).map_err(io::Error::from);
+        }
+    }
+
+    Err(ObuError::Leb128TooLong { offset: 0 }.into())
+}
+
+/// Appends `value` in leb128 encoding to `out`.
+pub(crate) fn write_leb128(out: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            break;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+/// Error returned when parsing malformed OBUs.
+///
+/// All offsets are byte offsets relative to the start of the parsed buffer.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ObuError {
+    /// The OBU header is truncated.
+    TruncatedHeader { offset: usize },
+    /// The forbidden bit of the OBU header is set.
+    ForbiddenBit { offset: usize },
+    /// A leb128 value is truncated.
+    TruncatedLeb128 { offset: usize },
+    /// A leb128 value is longer than 8 bytes.
+    Leb128TooLong { offset: usize },
+    /// A leb128 value does not fit into 32 bits.
+    Leb128Overflow { offset: usize },
+    /// The OBU size is larger than the remaining data.
+    TruncatedPayload {
+        offset: usize,
+        size: u64,
+        available: usize,
+    },
+}
+
+impl ObuError {
+    /// Byte offset of the OBU header or leb128 value the error occurred at.
+    pub fn offset(&self) -> usize {
+        match *self {
+            ObuError::TruncatedHeader { offset }
+            | ObuError::ForbiddenBit { offset }
+            | ObuError::TruncatedLeb128 { offset }
+            | ObuError::Leb128TooLong { offset }
+            | ObuError::Leb128Overflow { offset }
+            | ObuError::TruncatedPayload { offset, .. } => offset,
+        }
+    }
+
+    pub(crate) fn with_offset(mut self, base: usize) -> Self {
+        match &mut self {
+            ObuError::TruncatedHeader { offset }
+            | ObuError::ForbiddenBit { offset }
+            | ObuError::TruncatedLeb128 { offset }
+            | ObuError::Leb128TooLong { offset }
+            | ObuError::Leb128Overflow { offset }
+            | ObuError::TruncatedPayload { offset, .. } => *offset += base,
+        }
+        self
+    }
+}
+
+impl fmt::Display for ObuError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ObuError::TruncatedHeader { offset } => {
+                write!(fmt, "Truncated OBU header at offset {}", offset)
+            }
+            ObuError::ForbiddenBit { offset } => {
+                write!(fmt, "Forbidden bit set in OBU header at offset {}", offset)
+            }
+            ObuError::TruncatedLeb128 { offset } => {
+                write!(fmt, "Truncated leb128 value at offset {}", offset)
+            }
+            ObuError::Leb128TooLong { offset } => {
+                write!(fmt, "leb128 value longer than 8 bytes at offset {}", offset)
+            }
+            ObuError::Leb128Overflow { offset } => {
+                write!(fmt, "leb128 value larger than 32 bits at offset {}", offset)
+            }
+            ObuError::TruncatedPayload {
+                offset,
+                size,
+                available,
+            } => write!(
+                fmt,
+                "OBU at offset {} has size {} but only {} bytes are available",
+                offset, size, available
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ObuError {}
+
+impl From<ObuError> for io::Error {
+    fn from(err: ObuError) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ivf;
+
+    static TEST_FILE_420_8: &[u8] = include_bytes!("../test-420-8.ivf");
+
+    #[test]
+    fn test_iter() {
+        let packets = ivf::Reader::new(TEST_FILE_420_8)
+            .unwrap()
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+
+        let types = iter(&packets[0].data)
+            .map(|obu| obu.unwrap().obu_type())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            types,
+            [
+                Some(ObuType::TemporalDelimiter),
+                Some(ObuType::SequenceHeader),
+                Some(ObuType::Frame),
+            ]
+        );
+
+        for packet in &packets {
+            let mut end = 0;
+            for obu in iter(&packet.data) {
+                let obu = obu.unwrap();
+                assert_eq!(obu.range.start, end);
+                assert!(obu.header.has_size_field);
+                assert_eq!(obu.header.extension, None);
+                assert_eq!(obu.data(), &packet.data[obu.range.clone()]);
+                end = obu.range.end;
+            }
+            assert_eq!(end, packet.data.len());
+        }
+    }
+
+    #[test]
+    fn test_header() {
+        // Padding OBU with extension and without size field
+        let buf = [0x7c, 0x68, 0xaa, 0xbb];
+        let obus = iter(&buf).collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(obus.len(), 1);
+        assert_eq!(
+            obus[0].header,
+            ObuHeader {
+                raw_type: 15,
+                extension: Some(ObuExtension {
+                    temporal_id: 3,
+                    spatial_id: 1,
+                }),
+                has_size_field: false,
+            }
+        );
+        assert_eq!(obus[0].obu_type(), Some(ObuType::Padding));
+        assert_eq!(obus[0].payload(), [0xaa, 0xbb]);
+
+        // Reserved OBU type
+        let (header, len) = ObuHeader::parse(&[0x4a]).unwrap();
+        assert_eq!(header.raw_type, 9);
+        assert_eq!(header.obu_type(), None);
+        assert_eq!(len, 1);
+    }
+
+    #[test]
+    fn test_errors() {
+        let errors = |buf: &[u8]| iter(buf).filter_map(Result::err).collect::<Vec<_>>();
+
+        assert_eq!(
+            errors(&[0x12, 0x00, 0x92, 0x00]),
+            [ObuError::ForbiddenBit { offset: 2 }]
+        );
+        assert_eq!(
+            errors(&[0x12, 0x00, 0x16]),
+            [ObuError::TruncatedHeader { offset: 2 }]
+        );
+        assert_eq!(
+            errors(&[0x12, 0x00, 0x7a, 0x80]),
+            [ObuError::TruncatedLeb128 { offset: 3 }]
+        );
+        assert_eq!(
+            errors(&[0x7a, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x00]),
+            [ObuError::Leb128TooLong { offset: 1 }]
+        );
+        assert_eq!(
+            errors(&[0x7a, 0x80, 0x80, 0x80, 0x80, 0x10]),
+            [ObuError::Leb128Overflow { offset: 1 }]
+        );
+        assert_eq!(
+            errors(&[0x12, 0x00, 0x7a, 0x05, 0x00, 0x00]),
+            [ObuError::TruncatedPayload {
+                offset: 2,
+                size: 5,
+                available: 2,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_leb128() {
+        for value in [0, 1, 127, 128, 16383, 16384, u32::MAX as u64] {
+            let mut buf = Vec::new();
+            write_leb128(&mut buf, value);
+            assert_eq!(leb128(&buf), Ok((value, buf.len())));
+            assert_eq!(
+                read_leb128(&mut &buf[..]).unwrap(),
+                Some((value, buf.len()))
+            );
+        }
+
+        // Non-minimal encodings are allowed
+        assert_eq!(leb128(&[0x81, 0x80, 0x00]), Ok((1, 3)));
+    }
+}
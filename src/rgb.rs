@@ -0,0 +1,601 @@
+//! Conversion of pictures to RGB.
+
+use crate::pixel::{ChromaLocation, MatrixCoefficients, YUVRange};
+use crate::{Error, Picture, PictureAllocator, PlanarImageComponent, PlaneGeometry};
+
+/// Conversion from the YUV representation of a picture to RGB.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Matrix {
+    YCbCr { kr: f32, kb: f32 },
+    Identity,
+    YCgCo,
+}
+
+impl Matrix {
+    fn new(matrix_coefficients: MatrixCoefficients) -> Self {
+        match matrix_coefficients {
+            MatrixCoefficients::Identity => Matrix::Identity,
+            MatrixCoefficients::YCgCo => Matrix::YCgCo,
+            MatrixCoefficients::BT709 => Matrix::YCbCr {
+                kr: 0.2126,
+                kb: 0.0722,
+            },
+            MatrixCoefficients::BT470M => Matrix::YCbCr { kr: 0.30, kb: 0.11 },
+            MatrixCoefficients::ST240M => Matrix::YCbCr {
+                kr: 0.212,
+                kb: 0.087,
+            },
+            // Constant luminance and ICtCp can't be expressed as a matrix, use the matrix for the
+            // same primaries as closest approximation
+            MatrixCoefficients::BT2020NonConstantLuminance
+            | MatrixCoefficients::BT2020ConstantLuminance
+            | MatrixCoefficients::ICtCp => Matrix::YCbCr {
+                kr: 0.2627,
+                kb: 0.0593,
+            },
+            // BT.601 and anything unknown. ST 2085 and the chromaticity derived matrices depend on
+            // the color primaries and are not supported either, these also fall back to BT.601.
+            _ => Matrix::YCbCr {
+                kr: 0.299,
+                kb: 0.114,
+            },
+        }
+    }
+}
+
+/// Planes and color properties of the picture to convert.
+#[derive(Debug, Clone)]
+pub(crate) struct Source<'a> {
+    pub(crate) planes: [&'a [u8]; 3],
+    pub(crate) luma: PlaneGeometry,
+    /// Geometry of both chroma planes, `None` for monochrome pictures.
+    pub(crate) chroma: Option<PlaneGeometry>,
+    pub(crate) bits_per_component: usize,
+    pub(crate) matrix_coefficients: MatrixCoefficients,
+    pub(crate) color_range: YUVRange,
+    pub(crate) chroma_location: ChromaLocation,
+}
+
+impl<'a> Source<'a> {
+    pub(crate) fn from_picture<A: PictureAllocator>(
+        picture: &Picture<A>,
+        planes: &'a [crate::Plane<A>; 3],
+    ) -> Self {
+        Source {
+            planes: [&planes[0], &planes[1], &planes[2]],
+            luma: picture
+                .plane_geometry(PlanarImageComponent::Y)
+                .expect("luma plane"),
+            chroma: picture.plane_geometry(PlanarImageComponent::U),
+            bits_per_component: picture
+                .bits_per_component()
+                .map(|bpc| bpc.0)
+                .unwrap_or_else(|| picture.bit_depth()),
+            matrix_coefficients: picture.matrix_coefficients(),
+            color_range: picture.color_range(),
+            chroma_location: picture.chroma_sample_position(),
+        }
+    }
+
+    fn sample(&self, plane: usize, x: usize, y: usize) -> f32 {
+        let data = self.planes[plane];
+        let geometry = match plane {
+            0 => &self.luma,
+            _ => self.chroma.as_ref().expect("chroma plane"),
+        };
+        let pos = y * geometry.stride + x * geometry.bytes_per_sample;
+        if geometry.bytes_per_sample == 2 {
+            f32::from(u16::from_ne_bytes([data[pos], data[pos + 1]]))
+        } else {
+            f32::from(data[pos])
+        }
+    }
+}
+
+/// Position of the two chroma samples to interpolate for a luma sample, together with the weight
+/// of the second one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Tap {
+    first: usize,
+    second: usize,
+    weight: f32,
+}
+
+/// Computes the interpolation taps for upsampling `chroma_len` subsampled chroma samples to
+/// `len` luma samples.
+///
+/// `offset` is the position of the first chroma sample in luma samples.
+fn taps(len: usize, chroma_len: usize, subsampled: bool, offset: f32) -> Vec<Tap> {
+    (0..len)
+        .map(|i| {
+            if !subsampled {
+                return Tap {
+                    first: i,
+                    second: i,
+                    weight: 0.0,
+                };
+            }
+
+            let pos = ((i as f32 - offset) / 2.0).max(0.0);
+            let first = (pos as usize).min(chroma_len - 1);
+            let second = (first + 1).min(chroma_len - 1);
+            Tap {
+                first,
+                second,
+                weight: if second == first {
+                    0.0
+                } else {
+                    pos - first as f32
+                },
+            }
+        })
+        .collect()
+}
+
+/// Converts the picture to RGB.
+///
+/// Calls `f` with each row of normalized RGB values in the range `[0, 1]`. For monochrome pictures
+/// all three values are the same.
+pub(crate) fn convert(source: &Source, mut f: impl FnMut(&[[f32; 3]])) {
+    let bits = source.bits_per_component as i32;
+    let scale = (1 << (bits - 8)) as f32;
+    let max = ((1 << bits) - 1) as f32;
+    let (luma_offset, luma_range, chroma_range) = match source.color_range {
+        YUVRange::Limited => (16.0 * scale, 219.0 * scale, 224.0 * scale),
+        YUVRange::Full => (0.0, max, max),
+    };
+    let chroma_offset = (1 << (bits - 1)) as f32;
+    let matrix = Matrix::new(source.matrix_coefficients);
+
+    let width = source.luma.width;
+    let height = source.luma.height;
+    // For monochrome pictures the chroma taps are unused
+    let chroma = source.chroma.unwrap_or(source.luma);
+
+    let (offset_hor, offset_ver) = match source.chroma_location {
+        ChromaLocation::Left => (0.0, 0.5),
+        ChromaLocation::TopLeft => (0.0, 0.0),
+        ChromaLocation::Top => (0.5, 0.0),
+        ChromaLocation::BottomLeft => (0.0, 1.0),
+        ChromaLocation::Bottom => (0.5, 1.0),
+        ChromaLocation::Center | ChromaLocation::Unspecified => (0.5, 0.5),
+    };
+    let taps_hor = taps(width, chroma.width, chroma.x_shift == 1, offset_hor);
+    let taps_ver = taps(height, chroma.height, chroma.y_shift == 1, offset_ver);
+
+    let mut row = vec![[0.0f32; 3]; width];
+    for (y, tap_ver) in taps_ver.iter().enumerate() {
+        for (x, (out, tap_hor)) in row.iter_mut().zip(&taps_hor).enumerate() {
+            let luma = (source.sample(0, x, y) - luma_offset) / luma_range;
+            if source.chroma.is_none() {
+                *out = [luma; 3];
+                continue;
+            }
+
+            let chroma = |plane: usize| {
+                let row = |y: usize| {
+                    let first = source.sample(plane, tap_hor.first, y);
+                    let second = source.sample(plane, tap_hor.second, y);
+                    first + (second - first) * tap_hor.weight
+                };
+                let first = row(tap_ver.first);
+                let second = row(tap_ver.second);
+                first + (second - first) * tap_ver.weight
+            };
+            let u = chroma(1);
+            let v = chroma(2);
+
+            *out = match matrix {
+                Matrix::YCbCr { kr, kb } => {
+                    let cb = (u - chroma_offset) / chroma_range;
+                    let cr = (v - chroma_offset) / chroma_range;
+                    let r = luma + 2.0 * (1.0 - kr) * cr;
+                    let b = luma + 2.0 * (1.0 - kb) * cb;
+                    let g = (luma - kr * r - kb * b) / (1.0 - kr - kb);
+                    [r, g, b]
+                }
+                // GBR with all components scaled like luma
+                Matrix::Identity => [
+                    (v - luma_offset) / luma_range,
+                    luma,
+                    (u - luma_offset) / luma_range,
+                ],
+                Matrix::YCgCo => {
+                    let cg = (u - chroma_offset) / chroma_range;
+                    let co = (v - chroma_offset) / chroma_range;
+                    let t = luma - cg;
+                    [t + co, luma + cg, t - co]
+                }
+            };
+        }
+
+        f(&row);
+    }
+}
+
+/// Quantizes a normalized value to 8 bits.
+pub(crate) fn to_u8(v: f32) -> u8 {
+    (v.clamp(0.0, 1.0) * 255.0 + 0.5) as u8
+}
+
+/// Quantizes a normalized value to 16 bits.
+pub(crate) fn to_u16(v: f32) -> u16 {
+    (v.clamp(0.0, 1.0) * 65535.0 + 0.5) as u16
+}
+
+/// Pixel format for [`Picture::to_rgb`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RgbFormat {
+    /// Packed 8 bit red, green and blue.
+    Rgb24,
+    /// Packed 8 bit red, green, blue and opaque alpha.
+    Rgba,
+    /// Packed 8 bit blue, green, red and opaque alpha.
+    Bgra,
+    /// Packed native endian 16 bit red, green and blue.
+    Rgb48,
+    /// Separate 8 bit green, blue and red planes, stored after each other.
+    GbrPlanar,
+}
+
+impl RgbFormat {
+    /// Number of bytes per pixel in a row of a plane.
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            RgbFormat::Rgb24 => 3,
+            RgbFormat::Rgba | RgbFormat::Bgra => 4,
+            RgbFormat::Rgb48 => 6,
+            RgbFormat::GbrPlanar => 1,
+        }
+    }
+
+    /// Minimum buffer size for an image with `height` rows of `stride` bytes.
+    ///
+    /// This is `stride * height`, or three times that for [`RgbFormat::GbrPlanar`], or `None` if
+    /// the size overflows.
+    pub fn buffer_len(self, height: usize, stride: usize) -> Option<usize> {
+        let plane_len = stride.checked_mul(height)?;
+        match self {
+            RgbFormat::GbrPlanar => plane_len.checked_mul(3),
+            _ => Some(plane_len),
+        }
+    }
+}
+
+/// Converts the picture to RGB in `format` into `buf`.
+///
+/// Returns [`Error::InvalidArgument`] if `stride` or `buf` are too small for the picture.
+pub(crate) fn to_rgb(
+    source: &Source,
+    buf: &mut [u8],
+    stride: usize,
+    format: RgbFormat,
+) -> Result<(), Error> {
+    let width = source.luma.width;
+    let height = source.luma.height;
+    let buffer_len = format
+        .buffer_len(height, stride)
+        .ok_or(Error::InvalidArgument)?;
+    if stride < width * format.bytes_per_pixel() || buf.len() < buffer_len {
+        return Err(Error::InvalidArgument);
+    }
+
+    let plane_len = stride * height;
+    let mut y = 0;
+    convert(source, |row| {
+        let offset = y * stride;
+        match format {
+            RgbFormat::GbrPlanar => {
+                for (plane, channel) in [1, 2, 0].into_iter().enumerate() {
+                    let offset = plane * plane_len + offset;
+                    for (out, pixel) in buf[offset..offset + width].iter_mut().zip(row) {
+                        *out = to_u8(pixel[channel]);
+                    }
+                }
+            }
+            RgbFormat::Rgb48 => {
+                let out = &mut buf[offset..offset + 6 * width];
+                for (out, pixel) in out.chunks_exact_mut(6).zip(row) {
+                    for (out, &v) in out.chunks_exact_mut(2).zip(pixel) {
+                        out.copy_from_slice(&to_u16(v).to_ne_bytes());
+                    }
+                }
+            }
+            RgbFormat::Rgb24 | RgbFormat::Rgba | RgbFormat::Bgra => {
+                let bpp = format.bytes_per_pixel();
+                let out = &mut buf[offset..offset + bpp * width];
+                for (out, pixel) in out.chunks_exact_mut(bpp).zip(row) {
+                    let [r, g, b] = pixel.map(to_u8);
+                    match format {
+                        RgbFormat::Rgba => out.copy_from_slice(&[r, g, b, 255]),
+                        RgbFormat::Bgra => out.copy_from_slice(&[b, g, r, 255]),
+                        _ => out.copy_from_slice(&[r, g, b]),
+                    }
+                }
+            }
+        }
+        y += 1;
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::PixelLayout;
+
+    fn source<'a>(
+        planes: [&'a [u8]; 3],
+        width: usize,
+        height: usize,
+        pixel_layout: PixelLayout,
+    ) -> Source<'a> {
+        let geometry = |x_shift: u32, y_shift: u32| {
+            let width = (width + (1 << x_shift) - 1) >> x_shift;
+            PlaneGeometry {
+                width,
+                height: (height + (1 << y_shift) - 1) >> y_shift,
+                stride: width,
+                bytes_per_sample: 1,
+                x_shift,
+                y_shift,
+            }
+        };
+        Source {
+            planes,
+            luma: geometry(0, 0),
+            chroma: match pixel_layout {
+                PixelLayout::I400 => None,
+                PixelLayout::I420 => Some(geometry(1, 1)),
+                PixelLayout::I422 => Some(geometry(1, 0)),
+                PixelLayout::I444 => Some(geometry(0, 0)),
+            },
+            bits_per_component: 8,
+            matrix_coefficients: MatrixCoefficients::BT709,
+            color_range: YUVRange::Limited,
+            chroma_location: ChromaLocation::Center,
+        }
+    }
+
+    fn convert_to_u8(source: &Source) -> Vec<[u8; 3]> {
+        let mut out = Vec::new();
+        convert(source, |row| {
+            out.extend(row.iter().map(|p| [to_u8(p[0]), to_u8(p[1]), to_u8(p[2])]))
+        });
+        out
+    }
+
+    #[test]
+    fn test_matrices() {
+        // Black, white and BT.709 red in limited range
+        let src = source(
+            [&[16, 235, 63], &[128, 128, 102], &[128, 128, 240]],
+            3,
+            1,
+            PixelLayout::I444,
+        );
+        let out = convert_to_u8(&src);
+        assert_eq!(out[0], [0, 0, 0]);
+        assert_eq!(out[1], [255, 255, 255]);
+        assert!(out[2][0] >= 254 && out[2][1] <= 1 && out[2][2] <= 1);
+
+        // BT.601 red in full range
+        let mut src = source([&[76], &[85], &[255]], 1, 1, PixelLayout::I444);
+        src.matrix_coefficients = MatrixCoefficients::BT470BG;
+        src.color_range = YUVRange::Full;
+        let out = convert_to_u8(&src);
+        assert!(out[0][0] >= 254 && out[0][1] <= 1 && out[0][2] <= 1);
+
+        // GBR
+        let mut src = source([&[20], &[30], &[40]], 1, 1, PixelLayout::I444);
+        src.matrix_coefficients = MatrixCoefficients::Identity;
+        src.color_range = YUVRange::Full;
+        assert_eq!(convert_to_u8(&src), [[40, 20, 30]]);
+
+        // YCgCo green
+        let mut src = source([&[128], &[255], &[128]], 1, 1, PixelLayout::I444);
+        src.matrix_coefficients = MatrixCoefficients::YCgCo;
+        src.color_range = YUVRange::Full;
+        let out = convert_to_u8(&src);
+        assert!(out[0][0] <= 1 && out[0][1] == 255 && out[0][2] <= 1);
+    }
+
+    #[test]
+    fn test_high_bitdepth() {
+        let y = [64u16, 940, 512]
+            .iter()
+            .flat_map(|v| v.to_ne_bytes())
+            .collect::<Vec<_>>();
+        let c = [512u16; 3]
+            .iter()
+            .flat_map(|v| v.to_ne_bytes())
+            .collect::<Vec<_>>();
+        let mut src = source([&y, &c, &c], 3, 1, PixelLayout::I444);
+        for geometry in [Some(&mut src.luma), src.chroma.as_mut()]
+            .into_iter()
+            .flatten()
+        {
+            geometry.stride = 6;
+            geometry.bytes_per_sample = 2;
+        }
+        src.bits_per_component = 10;
+
+        let mut out = Vec::new();
+        convert(&src, |row| out.extend(row.iter().map(|p| to_u16(p[0]))));
+        assert_eq!(out[0], 0);
+        assert_eq!(out[1], 65535);
+        assert_eq!(out[2], to_u16((512.0 - 64.0) / 876.0));
+    }
+
+    #[test]
+    fn test_upsampling() {
+        // A single chroma row with a red and a white sample, 4:2:2
+        let src = source(
+            [&[63, 63, 63, 63], &[102, 128], &[240, 128]],
+            4,
+            1,
+            PixelLayout::I422,
+        );
+        let blue = |src: &Source| convert_to_u8(src).iter().map(|p| p[2]).collect::<Vec<_>>();
+
+        // Center siting interpolates every luma sample, left siting only the odd ones
+        let center = blue(&src);
+        let mut src = src;
+        src.chroma_location = ChromaLocation::Left;
+        let left = blue(&src);
+        assert!(center[0] <= 1);
+        assert_eq!(center[0], left[0]);
+        assert!(center[1] < left[1]);
+        assert!(center[2] < left[2]);
+        assert_eq!(center[3], left[3]);
+
+        // Same vertically for 4:2:0 with center and top left siting
+        let src = source(
+            [&[63; 8], &[102, 128], &[240, 128]],
+            2,
+            4,
+            PixelLayout::I420,
+        );
+        let center = blue(&src);
+        let mut src = src;
+        src.chroma_location = ChromaLocation::TopLeft;
+        let top_left = blue(&src);
+        assert_eq!(center.len(), 8);
+        assert!(center[0] <= 1);
+        assert_eq!(center[0], top_left[0]);
+        assert!(center[2] < top_left[2]);
+        assert!(center[4] < top_left[4]);
+        assert_eq!(center[6], top_left[6]);
+    }
+
+    #[test]
+    fn test_formats() {
+        // Red and blue next to each other
+        let mut src = source(
+            [&[76, 29], &[85, 255], &[255, 107]],
+            2,
+            1,
+            PixelLayout::I444,
+        );
+        src.matrix_coefficients = MatrixCoefficients::BT470BG;
+        src.color_range = YUVRange::Full;
+        let expected = convert_to_u8(&src);
+
+        let mut buf = [0; 10];
+        to_rgb(&src, &mut buf, 8, RgbFormat::Bgra).unwrap();
+        assert_eq!(
+            &buf[..4],
+            [expected[0][2], expected[0][1], expected[0][0], 255]
+        );
+        assert_eq!(
+            &buf[4..8],
+            [expected[1][2], expected[1][1], expected[1][0], 255]
+        );
+        // Padding is left untouched
+        assert_eq!(&buf[8..], [0, 0]);
+
+        let mut buf = [0; 8];
+        to_rgb(&src, &mut buf, 8, RgbFormat::Rgba).unwrap();
+        assert_eq!(
+            &buf[..4],
+            [expected[0][0], expected[0][1], expected[0][2], 255]
+        );
+
+        let mut buf = [0; 6];
+        to_rgb(&src, &mut buf, 6, RgbFormat::Rgb24).unwrap();
+        assert_eq!(buf, [expected[0], expected[1]].concat()[..]);
+
+        let mut buf = [0; 12];
+        to_rgb(&src, &mut buf, 12, RgbFormat::Rgb48).unwrap();
+        let rgb48 = buf
+            .chunks_exact(2)
+            .map(|v| u16::from_ne_bytes([v[0], v[1]]))
+            .collect::<Vec<_>>();
+        let mut expected48 = Vec::new();
+        convert(&src, |row| {
+            expected48.extend(row.iter().flat_map(|p| p.map(to_u16)))
+        });
+        assert_eq!(rgb48, expected48);
+
+        let mut buf = [0; 9];
+        to_rgb(&src, &mut buf, 3, RgbFormat::GbrPlanar).unwrap();
+        assert_eq!(
+            buf,
+            [
+                expected[0][1],
+                expected[1][1],
+                0,
+                expected[0][2],
+                expected[1][2],
+                0,
+                expected[0][0],
+                expected[1][0],
+                0
+            ]
+        );
+    }
+
+    #[test]
+    fn test_buffer_too_small() {
+        let src = source(
+            [&[16, 16], &[128, 128], &[128, 128]],
+            2,
+            1,
+            PixelLayout::I444,
+        );
+        assert_eq!(
+            to_rgb(&src, &mut [0; 7], 8, RgbFormat::Rgba),
+            Err(Error::InvalidArgument)
+        );
+        assert_eq!(
+            to_rgb(&src, &mut [0; 8], 7, RgbFormat::Rgba),
+            Err(Error::InvalidArgument)
+        );
+        assert_eq!(
+            to_rgb(&src, &mut [0; 8], usize::MAX, RgbFormat::Rgba),
+            Err(Error::InvalidArgument)
+        );
+        assert_eq!(
+            to_rgb(&src, &mut [0; 8], usize::MAX / 2, RgbFormat::GbrPlanar),
+            Err(Error::InvalidArgument)
+        );
+        assert_eq!(RgbFormat::Rgba.buffer_len(2, usize::MAX), None);
+    }
+
+    #[test]
+    fn test_taps() {
+        assert_eq!(
+            taps(4, 2, true, 0.5),
+            [
+                Tap {
+                    first: 0,
+                    second: 1,
+                    weight: 0.0
+                },
+                Tap {
+                    first: 0,
+                    second: 1,
+                    weight: 0.25
+                },
+                Tap {
+                    first: 0,
+                    second: 1,
+                    weight: 0.75
+                },
+                Tap {
+                    first: 1,
+                    second: 1,
+                    weight: 0.0
+                },
+            ]
+        );
+        assert_eq!(
+            taps(3, 2, true, 0.0)
+                .iter()
+                .map(|t| t.weight)
+                .collect::<Vec<_>>(),
+            [0.0, 0.5, 0.0]
+        );
+    }
+}
@@ -0,0 +1,228 @@
+//! Reading of AV1 low overhead bitstreams.
+//!
+//! Low overhead bitstreams as defined in section 5 of the AV1 specification are plain
+//! concatenations of OBUs with size fields, without any container framing. Temporal units are
+//! split at temporal delimiter OBUs.
+
+use crate::obu::{read_leb128, ObuHeader, ObuType, OBU_EXTENSION_FLAG};
+use std::io::{self, Read};
+
+/// A temporal unit of a low overhead bitstream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemporalUnit {
+    /// Temporal unit data.
+    pub data: Box<[u8]>,
+    /// Byte offset of the temporal unit in the stream.
+    ///
+    /// This can be passed as `offset` to [`Decoder::send_data`](crate::Decoder::send_data).
+    pub offset: u64,
+}
+
+#[derive(Debug)]
+struct Obu {
+    data: Vec<u8>,
+    offset: u64,
+}
+
+impl Obu {
+    fn is_temporal_delimiter(&self) -> bool {
+        matches!(
+            ObuHeader::parse(&self.data),
+            Ok((header, _)) if header.obu_type() == Some(ObuType::TemporalDelimiter)
+        )
+    }
+}
+
+/// Low overhead bitstream reader.
+#[derive(Debug)]
+pub struct Reader<R: Read> {
+    reader: R,
+    pos: u64,
+    pending: Option<Obu>,
+}
+
+impl<R: Read> Reader<R> {
+    /// Creates a new [`Reader`] reading from `reader`.
+    pub fn new(reader: R) -> Self {
+        Reader {
+            reader,
+            pos: 0,
+            pending: None,
+        }
+    }
+
+    /// Reads the next temporal unit.
+    ///
+    /// A temporal unit starts at a temporal delimiter OBU and contains all OBUs up to the next
+    /// one. OBUs before the first temporal delimiter form a temporal unit of their own.
+    ///
+    /// Returns `Ok(None)` at the end of the stream.
+    pub fn read_temporal_unit(&mut self) -> io::Result<Option<TemporalUnit>> {
+        let first = match self.pending.take() {
+            Some(obu) => obu,
+            None => match self.read_obu()? {
+                Some(obu) => obu,
+                None => return Ok(None),
+            },
+        };
+
+        let offset = first.offset;
+        let mut data = first.data;
+        while let Some(obu) = self.read_obu()? {
+            if obu.is_temporal_delimiter() {
+                self.pending = Some(obu);
+                break;
+            }
+            data.extend_from_slice(&obu.data);
+        }
+
+        Ok(Some(TemporalUnit {
+            data: data.into_boxed_slice(),
+            offset,
+        }))
+    }
+
+    fn read_obu(&mut self) -> io::Result<Option<Obu>> {
+        let offset = self.pos;
+        let mut data = Vec::new();
+        let mut reader = Tee {
+            reader: &mut self.reader,
+            buf: &mut data,
+        };
+
+        let mut header = [0u8; 2];
+        if reader.read(&mut header[..1])? == 0 {
+            return Ok(None);
+        }
+        let header_len = if header[0] & OBU_EXTENSION_FLAG != 0 {
+            2
+        } else {
+            1
+        };
+        reader.read_exact(&mut header[1..header_len])?;
+        let (header, _) =
+            ObuHeader::parse(&header[..header_len]).map_err(|e| e.with_offset(offset as usize))?;
+
+        if !header.has_size_field {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("OBU without size field at offset {}", offset),
+            ));
+        }
+
+        let (size, _) = read_leb128(&mut reader)?
+            .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "Truncated OBU header"))?;
+
+        let read = io::copy(&mut (&mut reader).take(size), &mut io::sink())?;
+        if read < size {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Truncated OBU",
+            ));
+        }
+
+        self.pos += data.len() as u64;
+
+        Ok(Some(Obu { data, offset }))
+    }
+
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: Read> Iterator for Reader<R> {
+    type Item = io::Result<TemporalUnit>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_temporal_unit().transpose()
+    }
+}
+
+/// Reader that keeps a copy of everything read.
+struct Tee<'a, R: Read> {
+    reader: &'a mut R,
+    buf: &'a mut Vec<u8>,
+}
+
+impl<R: Read> Read for Tee<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.reader.read(buf)?;
+        self.buf.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ivf;
+
+    static TEST_FILE_420_8: &[u8] = include_bytes!("../test-420-8.ivf");
+
+    #[test]
+    fn test_read() {
+        let packets = ivf::Reader::new(TEST_FILE_420_8)
+            .unwrap()
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+        let stream = packets
+            .iter()
+            .flat_map(|p| p.data.iter().copied())
+            .collect::<Vec<_>>();
+
+        let tus = Reader::new(&stream[..])
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(tus.len(), packets.len());
+
+        let mut offset = 0;
+        for (tu, packet) in tus.iter().zip(&packets) {
+            assert_eq!(tu.data, packet.data);
+            assert_eq!(tu.offset, offset);
+            offset += packet.data.len() as u64;
+        }
+    }
+
+    #[test]
+    fn test_no_temporal_delimiter() {
+        // Padding OBU followed by a temporal delimiter
+        let stream = [0x7a, 0x01, 0x00, 0x12, 0x00];
+        let tus = Reader::new(&stream[..])
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(
+            tus,
+            [
+                TemporalUnit {
+                    data: Box::new([0x7a, 0x01, 0x00]),
+                    offset: 0,
+                },
+                TemporalUnit {
+                    data: Box::new([0x12, 0x00]),
+                    offset: 3,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        // OBU without size field
+        let err = Reader::new(&[0x10][..]).read_temporal_unit().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // Truncated OBU
+        let err = Reader::new(&[0x12, 0x00, 0x7a, 0x05, 0x00][..])
+            .read_temporal_unit()
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        // Truncated size field
+        let err = Reader::new(&[0x12, 0x80][..])
+            .read_temporal_unit()
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
@@ -0,0 +1,317 @@
+//! Packing of pictures into semi-planar formats.
+
+use crate::{Error, Picture, PictureAllocator, PlanarImageComponent, PlaneGeometry};
+
+/// Semi-planar pixel format for [`Picture::to_semi_planar`].
+///
+/// All formats consist of a luma plane followed by a plane with interleaved U and V samples, both
+/// with 4:2:0 subsampling.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SemiPlanarFormat {
+    /// 8 bit samples, for 8 bit pictures.
+    Nv12,
+    /// Little endian 16 bit samples with the 10 significant bits in the most significant bits, for
+    /// 8 and 10 bit pictures.
+    P010,
+    /// Little endian 16 bit samples with the significant bits in the most significant bits, for
+    /// pictures of any bit depth.
+    P016,
+}
+
+impl SemiPlanarFormat {
+    /// Number of bytes per sample.
+    pub fn bytes_per_sample(self) -> usize {
+        match self {
+            SemiPlanarFormat::Nv12 => 1,
+            SemiPlanarFormat::P010 | SemiPlanarFormat::P016 => 2,
+        }
+    }
+
+    /// Minimum luma buffer size for an image with `height` rows of `stride` bytes, or `None` if
+    /// the size overflows.
+    pub fn luma_len(self, height: usize, stride: usize) -> Option<usize> {
+        stride.checked_mul(height)
+    }
+
+    /// Minimum chroma buffer size for an image with `height` luma rows and `stride` bytes per
+    /// chroma row, or `None` if the size overflows.
+    pub fn chroma_len(self, height: usize, stride: usize) -> Option<usize> {
+        stride.checked_mul(height.div_ceil(2))
+    }
+
+    /// Number of bits samples with `bits_per_component` bits are shifted to the left, or `None` if
+    /// the bit depth is not supported by the format.
+    fn shift(self, bits_per_component: usize) -> Option<u32> {
+        match self {
+            SemiPlanarFormat::Nv12 if bits_per_component == 8 => Some(0),
+            SemiPlanarFormat::P010 if bits_per_component <= 10 => {
+                Some(16 - bits_per_component as u32)
+            }
+            SemiPlanarFormat::P016 if bits_per_component <= 16 => {
+                Some(16 - bits_per_component as u32)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Checks that `dst` with `dst_stride` bytes per row fits `planes` interleaved planes with
+/// `geometry`.
+fn check_dst(
+    planes: usize,
+    geometry: &PlaneGeometry,
+    dst: &[u8],
+    dst_stride: usize,
+    format: SemiPlanarFormat,
+) -> Result<(), Error> {
+    let row_len = geometry
+        .width
+        .checked_mul(planes * format.bytes_per_sample())
+        .ok_or(Error::InvalidArgument)?;
+    let len = dst_stride
+        .checked_mul(geometry.height)
+        .ok_or(Error::InvalidArgument)?;
+    if dst_stride < row_len || dst.len() < len {
+        return Err(Error::InvalidArgument);
+    }
+
+    Ok(())
+}
+
+/// Interleaves the samples of `planes` with `geometry` into `dst`, row by row.
+///
+/// `dst` must have been checked with [`check_dst`] before.
+fn interleave(
+    planes: &[&[u8]],
+    geometry: &PlaneGeometry,
+    dst: &mut [u8],
+    dst_stride: usize,
+    format: SemiPlanarFormat,
+    shift: u32,
+) {
+    let bytes_per_sample = format.bytes_per_sample();
+    debug_assert!(check_dst(planes.len(), geometry, dst, dst_stride, format).is_ok());
+
+    let sample = |plane: &[u8], pos: usize| -> u16 {
+        if geometry.bytes_per_sample == 2 {
+            u16::from_ne_bytes([plane[2 * pos], plane[2 * pos + 1]])
+        } else {
+            u16::from(plane[pos])
+        }
+    };
+
+    for (y, row) in dst.chunks_mut(dst_stride).take(geometry.height).enumerate() {
+        let row = &mut row[..geometry.width * planes.len() * bytes_per_sample];
+        for (i, out) in row.chunks_exact_mut(bytes_per_sample).enumerate() {
+            let plane = planes[i % planes.len()];
+            let v = sample(&plane[y * geometry.stride..], i / planes.len());
+            match format {
+                SemiPlanarFormat::Nv12 => out[0] = v as u8,
+                SemiPlanarFormat::P010 | SemiPlanarFormat::P016 => {
+                    out.copy_from_slice(&(v << shift).to_le_bytes())
+                }
+            }
+        }
+    }
+}
+
+/// Packs the luma plane of `picture` into `luma`, if any, and its chroma planes into `chroma`.
+///
+/// Nothing is written if any of the arguments is invalid.
+pub(crate) fn pack<A: PictureAllocator>(
+    picture: &Picture<A>,
+    luma: Option<(&mut [u8], usize)>,
+    chroma: &mut [u8],
+    chroma_stride: usize,
+    format: SemiPlanarFormat,
+) -> Result<(), Error> {
+    let chroma_geometry = match picture.plane_geometry(PlanarImageComponent::U) {
+        Some(geometry) if (geometry.x_shift, geometry.y_shift) == (1, 1) => geometry,
+        _ => return Err(Error::InvalidArgument),
+    };
+    let bits_per_component = picture
+        .bits_per_component()
+        .map(|bpc| bpc.0)
+        .unwrap_or_else(|| picture.bit_depth());
+    let shift = format
+        .shift(bits_per_component)
+        .ok_or(Error::InvalidArgument)?;
+
+    // Without luma the decoded luma plane is used as is, which is only valid for NV12
+    if luma.is_none() && shift != 0 {
+        return Err(Error::InvalidArgument);
+    }
+
+    let luma_geometry = picture
+        .plane_geometry(PlanarImageComponent::Y)
+        .expect("luma plane");
+    if let Some((luma, luma_stride)) = &luma {
+        check_dst(1, &luma_geometry, luma, *luma_stride, format)?;
+    }
+    check_dst(2, &chroma_geometry, chroma, chroma_stride, format)?;
+
+    if let Some((luma, luma_stride)) = luma {
+        let y = picture.plane(PlanarImageComponent::Y);
+        interleave(&[&y], &luma_geometry, luma, luma_stride, format, shift);
+    }
+
+    let u = picture.plane(PlanarImageComponent::U);
+    let v = picture.plane(PlanarImageComponent::V);
+    interleave(
+        &[&u, &v],
+        &chroma_geometry,
+        chroma,
+        chroma_stride,
+        format,
+        shift,
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Decoder;
+
+    static TEST_FILE_420_8: &[u8] = include_bytes!("../test-420-8.ivf");
+    static TEST_FILE_420_12: &[u8] = include_bytes!("../test-420-12.ivf");
+
+    #[test]
+    fn test_shift() {
+        assert_eq!(SemiPlanarFormat::Nv12.shift(8), Some(0));
+        assert_eq!(SemiPlanarFormat::Nv12.shift(10), None);
+        assert_eq!(SemiPlanarFormat::P010.shift(8), Some(8));
+        assert_eq!(SemiPlanarFormat::P010.shift(10), Some(6));
+        assert_eq!(SemiPlanarFormat::P010.shift(12), None);
+        assert_eq!(SemiPlanarFormat::P016.shift(12), Some(4));
+    }
+
+    fn geometry(
+        width: usize,
+        height: usize,
+        stride: usize,
+        bytes_per_sample: usize,
+    ) -> PlaneGeometry {
+        PlaneGeometry {
+            width,
+            height,
+            stride,
+            bytes_per_sample,
+            x_shift: 1,
+            y_shift: 1,
+        }
+    }
+
+    #[test]
+    fn test_interleave() {
+        // 3x1 chroma with one sample of stride padding
+        let u = [1, 2, 3, 0xff];
+        let v = [4, 5, 6, 0xff];
+        let mut dst = [0; 8];
+        let chroma = geometry(3, 1, 4, 1);
+        interleave(&[&u, &v], &chroma, &mut dst, 8, SemiPlanarFormat::Nv12, 0);
+        assert_eq!(dst, [1, 4, 2, 5, 3, 6, 0, 0]);
+
+        // 10 bit luma with two rows
+        let y = [0x3ffu16, 1, 2, 3]
+            .iter()
+            .flat_map(|v| v.to_ne_bytes())
+            .collect::<Vec<_>>();
+        let mut dst = [0; 8];
+        let luma = geometry(2, 2, 4, 2);
+        interleave(&[&y], &luma, &mut dst, 4, SemiPlanarFormat::P010, 6);
+        assert_eq!(dst, [0xc0, 0xff, 0x40, 0x00, 0x80, 0x00, 0xc0, 0x00]);
+    }
+
+    #[test]
+    fn test_check_dst() {
+        let chroma = geometry(3, 1, 4, 1);
+        assert_eq!(
+            check_dst(2, &chroma, &[0; 8], 6, SemiPlanarFormat::Nv12),
+            Ok(())
+        );
+        // Stride too small
+        assert_eq!(
+            check_dst(2, &chroma, &[0; 8], 5, SemiPlanarFormat::Nv12),
+            Err(Error::InvalidArgument)
+        );
+        // Buffer too small
+        assert_eq!(
+            check_dst(2, &chroma, &[0; 11], 12, SemiPlanarFormat::P010),
+            Err(Error::InvalidArgument)
+        );
+        // Buffer size overflows
+        assert_eq!(
+            check_dst(
+                2,
+                &geometry(3, 2, 4, 1),
+                &[0; 8],
+                usize::MAX,
+                SemiPlanarFormat::Nv12
+            ),
+            Err(Error::InvalidArgument)
+        );
+        assert_eq!(SemiPlanarFormat::Nv12.luma_len(2, usize::MAX), None);
+        assert_eq!(SemiPlanarFormat::Nv12.chroma_len(3, usize::MAX), None);
+    }
+
+    #[test]
+    fn test_pack() {
+        let mut pictures = Vec::new();
+        crate::test::decode_file(TEST_FILE_420_8, Decoder::new().unwrap(), &mut pictures);
+        let picture = &pictures[0];
+
+        let mut luma = vec![0; 320 * 240];
+        let mut chroma = vec![0; SemiPlanarFormat::Nv12.chroma_len(240, 320).unwrap()];
+        picture
+            .to_semi_planar(&mut luma, 320, &mut chroma, 320, SemiPlanarFormat::Nv12)
+            .unwrap();
+        let y = picture.plane(PlanarImageComponent::Y);
+        let u = picture.plane(PlanarImageComponent::U);
+        let v = picture.plane(PlanarImageComponent::V);
+        assert_eq!(&luma[..320], &y[..320]);
+        assert_eq!((chroma[0], chroma[1]), (u[0], v[0]));
+
+        let mut chroma_only = vec![0; chroma.len()];
+        picture
+            .to_semi_planar_chroma(&mut chroma_only, 320, SemiPlanarFormat::Nv12)
+            .unwrap();
+        assert_eq!(chroma_only, chroma);
+
+        // Too small luma buffer
+        assert_eq!(
+            picture.to_semi_planar(
+                &mut luma[1..],
+                320,
+                &mut chroma,
+                320,
+                SemiPlanarFormat::Nv12
+            ),
+            Err(Error::InvalidArgument)
+        );
+
+        let mut pictures = Vec::new();
+        crate::test::decode_file(TEST_FILE_420_12, Decoder::new().unwrap(), &mut pictures);
+        let picture = &pictures[0];
+        let mut luma = vec![0; 2 * 320 * 240];
+        let mut chroma = vec![0; 2 * 320 * 120];
+        assert_eq!(
+            picture.to_semi_planar(&mut luma, 640, &mut chroma, 640, SemiPlanarFormat::P010),
+            Err(Error::InvalidArgument)
+        );
+        picture
+            .to_semi_planar(&mut luma, 640, &mut chroma, 640, SemiPlanarFormat::P016)
+            .unwrap();
+        // Only NV12 can use the decoded luma plane as is
+        assert_eq!(
+            picture.to_semi_planar_chroma(&mut chroma, 640, SemiPlanarFormat::P016),
+            Err(Error::InvalidArgument)
+        );
+        let y = picture.plane(PlanarImageComponent::Y);
+        assert_eq!(
+            u16::from_le_bytes([luma[0], luma[1]]),
+            u16::from_ne_bytes([y[0], y[1]]) << 4
+        );
+    }
+}
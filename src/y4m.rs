@@ -0,0 +1,319 @@
+//! Writing of YUV4MPEG2 streams.
+//!
+//! The output is compatible with the `y4m` muxer of the `dav1d` command line tool: all planes are
+//! written without stride padding and samples with more than 8 bits are stored as 16 bit little
+//! endian integers.
+
+use crate::pixel::{ChromaLocation, YUVRange};
+use crate::{ivf, yuv, Picture, PictureAllocator, PixelLayout};
+use std::fmt;
+use std::io::{self, Write};
+
+const SIGNATURE: &str = "YUV4MPEG2";
+const FRAME_HEADER: &[u8] = b"FRAME\n";
+
+/// YUV4MPEG2 stream header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Header {
+    /// Width of the frames.
+    pub width: u32,
+    /// Height of the frames.
+    pub height: u32,
+    /// Numerator of the frame rate.
+    pub frame_rate_num: u32,
+    /// Denominator of the frame rate.
+    pub frame_rate_den: u32,
+    /// Numerator of the pixel aspect ratio, `0` if unknown.
+    pub pixel_aspect_num: u64,
+    /// Denominator of the pixel aspect ratio, `0` if unknown.
+    pub pixel_aspect_den: u64,
+    /// Pixel layout of the frames.
+    pub pixel_layout: PixelLayout,
+    /// Bits used per component, 8, 10 or 12.
+    pub bits_per_component: usize,
+    /// Sample position for subsampled chroma.
+    ///
+    /// For 8 bit 4:2:0 this selects the `C` tag: `Left` for `420mpeg2`, `TopLeft` for co-located
+    /// chroma and `420`, and `420jpeg` otherwise.
+    pub chroma_location: ChromaLocation,
+    /// YUV color range.
+    pub color_range: YUVRange,
+}
+
+impl Header {
+    /// Creates the header for a stream of pictures like `picture` with the given frame rate.
+    ///
+    /// The pixel aspect ratio is derived from the render size of the frame header.
+    pub fn from_picture<A: PictureAllocator>(
+        picture: &Picture<A>,
+        frame_rate_num: u32,
+        frame_rate_den: u32,
+    ) -> Self {
+        let width = picture.width();
+        let height = picture.height();
+        let (render_width, render_height) = picture.render_size();
+
+        // Same as dav1d's y4m muxer
+        let aspect_num = u64::from(height) * u64::from(render_width);
+        let aspect_den = u64::from(width) * u64::from(render_height);
+        let gcd = gcd(aspect_num, aspect_den);
+
+        Header {
+            width,
+            height,
+            frame_rate_num,
+            frame_rate_den,
+            pixel_aspect_num: aspect_num.checked_div(gcd).unwrap_or(0),
+            pixel_aspect_den: aspect_den.checked_div(gcd).unwrap_or(0),
+            pixel_layout: picture.pixel_layout(),
+            bits_per_component: bits_per_component(picture),
+            chroma_location: picture.chroma_sample_position(),
+            color_range: picture.color_range(),
+        }
+    }
+
+    /// Value of the `C` tag describing the pixel layout and bit depth.
+    pub fn colorspace(&self) -> &'static str {
+        match (self.pixel_layout, self.bits_per_component) {
+            (PixelLayout::I400, 8) => "mono",
+            (PixelLayout::I400, 10) => "mono10",
+            (PixelLayout::I400, _) => "mono12",
+            (PixelLayout::I420, 8) => match self.chroma_location {
+                ChromaLocation::Left => "420mpeg2",
+                ChromaLocation::TopLeft => "420",
+                _ => "420jpeg",
+            },
+            (PixelLayout::I420, 10) => "420p10",
+            (PixelLayout::I420, _) => "420p12",
+            (PixelLayout::I422, 8) => "422",
+            (PixelLayout::I422, 10) => "422p10",
+            (PixelLayout::I422, _) => "422p12",
+            (PixelLayout::I444, 8) => "444",
+            (PixelLayout::I444, 10) => "444p10",
+            (PixelLayout::I444, _) => "444p12",
+        }
+    }
+}
+
+impl fmt::Display for Header {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} W{} H{} F{}:{} Ip A{}:{} C{} XCOLORRANGE={}",
+            SIGNATURE,
+            self.width,
+            self.height,
+            self.frame_rate_num,
+            self.frame_rate_den,
+            self.pixel_aspect_num,
+            self.pixel_aspect_den,
+            self.colorspace(),
+            match self.color_range {
+                YUVRange::Limited => "LIMITED",
+                YUVRange::Full => "FULL",
+            },
+        )
+    }
+}
+
+fn bits_per_component<A: PictureAllocator>(picture: &Picture<A>) -> usize {
+    picture
+        .bits_per_component()
+        .map(|bpc| bpc.0)
+        .unwrap_or_else(|| picture.bit_depth())
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// YUV4MPEG2 stream writer.
+///
+/// The stream header is written together with the first picture. All following pictures must
+/// have the same size, pixel layout and bit depth.
+#[derive(Debug)]
+pub struct Writer<W: Write> {
+    writer: W,
+    frame_rate_num: u32,
+    frame_rate_den: u32,
+    header: Option<Header>,
+    frame_count: u64,
+    buf: Vec<u8>,
+}
+
+impl<W: Write> Writer<W> {
+    /// Creates a new [`Writer`] with the given frame rate.
+    pub fn new(writer: W, frame_rate_num: u32, frame_rate_den: u32) -> Self {
+        Writer {
+            writer,
+            frame_rate_num,
+            frame_rate_den,
+            header: None,
+            frame_count: 0,
+            buf: Vec::new(),
+        }
+    }
+
+    /// Creates a new [`Writer`] with the frame rate taken from the timebase of an IVF file.
+    ///
+    /// The timebase is only the frame rate if every frame has a duration of one tick. Files with a
+    /// millisecond timebase result in `F1000:1`, use [`Writer::new`] with the actual frame rate if
+    /// it is known.
+    pub fn from_ivf_header(writer: W, header: &ivf::Header) -> Self {
+        Self::new(writer, header.timebase_den, header.timebase_num)
+    }
+
+    /// Stream header, once the first picture was written.
+    pub fn header(&self) -> Option<&Header> {
+        self.header.as_ref()
+    }
+
+    /// Writes a picture, preceded by the stream header if this is the first picture.
+    pub fn write_picture<A: PictureAllocator>(&mut self, picture: &Picture<A>) -> io::Result<()> {
+        match self.header {
+            None => {
+                let header =
+                    Header::from_picture(picture, self.frame_rate_num, self.frame_rate_den);
+                writeln!(self.writer, "{}", header)?;
+                self.header = Some(header);
+            }
+            Some(ref h)
+                if (h.width, h.height, h.pixel_layout, h.bits_per_component)
+                    != (
+                        picture.width(),
+                        picture.height(),
+                        picture.pixel_layout(),
+                        bits_per_component(picture),
+                    ) =>
+            {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "Picture parameters differ from the stream header",
+                ));
+            }
+            Some(_) => (),
+        }
+
+        self.writer.write_all(FRAME_HEADER)?;
+        let writer = &mut self.writer;
+        yuv::for_each_row(picture, &mut self.buf, |row| writer.write_all(row))?;
+
+        self.frame_count += 1;
+
+        Ok(())
+    }
+
+    /// Number of pictures written so far.
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    /// Flushes and returns the underlying writer.
+    pub fn into_inner(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Decoder;
+
+    static TEST_FILE_420_8: &[u8] = include_bytes!("../test-420-8.ivf");
+    static TEST_FILE_420_12: &[u8] = include_bytes!("../test-420-12.ivf");
+
+    fn header(pixel_layout: PixelLayout, bits_per_component: usize) -> Header {
+        Header {
+            width: 320,
+            height: 240,
+            frame_rate_num: 30000,
+            frame_rate_den: 1001,
+            pixel_aspect_num: 1,
+            pixel_aspect_den: 1,
+            pixel_layout,
+            bits_per_component,
+            chroma_location: ChromaLocation::Center,
+            color_range: YUVRange::Limited,
+        }
+    }
+
+    #[test]
+    fn test_header() {
+        let tags = [
+            (PixelLayout::I400, 8, "mono"),
+            (PixelLayout::I400, 10, "mono10"),
+            (PixelLayout::I400, 12, "mono12"),
+            (PixelLayout::I420, 8, "420jpeg"),
+            (PixelLayout::I420, 10, "420p10"),
+            (PixelLayout::I420, 12, "420p12"),
+            (PixelLayout::I422, 8, "422"),
+            (PixelLayout::I422, 10, "422p10"),
+            (PixelLayout::I422, 12, "422p12"),
+            (PixelLayout::I444, 8, "444"),
+            (PixelLayout::I444, 10, "444p10"),
+            (PixelLayout::I444, 12, "444p12"),
+        ];
+        for (layout, bpc, tag) in tags {
+            assert_eq!(header(layout, bpc).colorspace(), tag);
+        }
+
+        let mut h = header(PixelLayout::I420, 8);
+        h.chroma_location = ChromaLocation::Left;
+        assert_eq!(h.colorspace(), "420mpeg2");
+        h.chroma_location = ChromaLocation::TopLeft;
+        assert_eq!(h.colorspace(), "420");
+        // Only the 8 bit tags carry the chroma location
+        h.bits_per_component = 10;
+        assert_eq!(h.colorspace(), "420p10");
+
+        assert_eq!(
+            header(PixelLayout::I420, 8).to_string(),
+            "YUV4MPEG2 W320 H240 F30000:1001 Ip A1:1 C420jpeg XCOLORRANGE=LIMITED"
+        );
+        let mut h = header(PixelLayout::I444, 12);
+        h.color_range = YUVRange::Full;
+        assert_eq!(
+            h.to_string(),
+            "YUV4MPEG2 W320 H240 F30000:1001 Ip A1:1 C444p12 XCOLORRANGE=FULL"
+        );
+    }
+
+    fn decode_to_y4m(file: &[u8]) -> Vec<u8> {
+        let r = ivf::Reader::new(file).unwrap();
+        let mut w = Writer::from_ivf_header(Vec::new(), r.header());
+        let mut pictures = Vec::new();
+        crate::test::decode_file(file, Decoder::new().unwrap(), &mut pictures);
+        for p in &pictures {
+            w.write_picture(p).unwrap();
+        }
+
+        assert_eq!(w.frame_count(), 5);
+        w.into_inner().unwrap()
+    }
+
+    #[test]
+    fn test_write() {
+        for (file, colorspace, bytes_per_sample) in [
+            (TEST_FILE_420_8, "420jpeg", 1),
+            (TEST_FILE_420_12, "420p12", 2),
+        ] {
+            let y4m = decode_to_y4m(file);
+            let header = format!(
+                "YUV4MPEG2 W320 H240 F1000:1 Ip A1:1 C{} XCOLORRANGE=LIMITED\n",
+                colorspace
+            );
+            assert!(y4m.starts_with(header.as_bytes()));
+
+            let frame_len = FRAME_HEADER.len() + (320 * 240 + 2 * 160 * 120) * bytes_per_sample;
+            assert_eq!(y4m.len(), header.len() + 5 * frame_len);
+            for i in 0..5 {
+                let pos = header.len() + i * frame_len;
+                assert_eq!(&y4m[pos..pos + FRAME_HEADER.len()], FRAME_HEADER);
+            }
+        }
+    }
+}
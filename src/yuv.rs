@@ -0,0 +1,221 @@
+//! Writing of raw planar YUV data.
+//!
+//! The output is compatible with the `yuv` and `md5` muxers of the `dav1d` command line tool: the
+//! Y, U and V planes of each picture are written after each other without stride padding and
+//! samples with more than 8 bits are stored as 16 bit little endian integers.
+
+use crate::{Picture, PictureAllocator, PlanarImageComponent};
+#[cfg(feature = "md5")]
+use std::fmt;
+use std::io::{self, Write};
+
+/// Calls `f` with every row of the picture in output order.
+///
+/// `buf` is used for converting high bit depth samples to little endian on big endian targets.
+pub(crate) fn for_each_row<A: PictureAllocator>(
+    picture: &Picture<A>,
+    buf: &mut Vec<u8>,
+    mut f: impl FnMut(&[u8]) -> io::Result<()>,
+) -> io::Result<()> {
+    for component in [
+        PlanarImageComponent::Y,
+        PlanarImageComponent::U,
+        PlanarImageComponent::V,
+    ] {
+        let Some(geometry) = picture.plane_geometry(component) else {
+            continue;
+        };
+        let row_len = geometry.width * geometry.bytes_per_sample;
+
+        let plane = picture.plane(component);
+        for row in plane.chunks(geometry.stride).take(geometry.height) {
+            let row = &row[..row_len];
+            if geometry.bytes_per_sample == 2 && cfg!(target_endian = "big") {
+                buf.clear();
+                buf.extend(
+                    row.chunks_exact(2)
+                        .flat_map(|s| u16::from_ne_bytes([s[0], s[1]]).to_le_bytes()),
+                );
+                f(buf)?;
+            } else {
+                f(row)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Raw planar YUV writer.
+#[derive(Debug)]
+pub struct Writer<W: Write> {
+    writer: W,
+    frame_count: u64,
+    buf: Vec<u8>,
+}
+
+impl<W: Write> Writer<W> {
+    /// Creates a new [`Writer`].
+    pub fn new(writer: W) -> Self {
+        Writer {
+            writer,
+            frame_count: 0,
+            buf: Vec::new(),
+        }
+    }
+
+    /// Writes the planes of a picture.
+    pub fn write_picture<A: PictureAllocator>(&mut self, picture: &Picture<A>) -> io::Result<()> {
+        let writer = &mut self.writer;
+        for_each_row(picture, &mut self.buf, |row| writer.write_all(row))?;
+        self.frame_count += 1;
+
+        Ok(())
+    }
+
+    /// Number of pictures written so far.
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    /// Flushes and returns the underlying writer.
+    pub fn into_inner(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Streaming MD5 checksum over the raw planar YUV data of pictures.
+///
+/// The digest is identical to the one computed by `dav1d --muxer md5`.
+#[cfg(feature = "md5")]
+#[derive(Debug, Clone, Default)]
+pub struct Md5 {
+    hasher: md5::Md5,
+    frame_count: u64,
+    buf: Vec<u8>,
+}
+
+#[cfg(feature = "md5")]
+impl Md5 {
+    /// Creates a new [`Md5`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the planes of a picture to the checksum.
+    pub fn write_picture<A: PictureAllocator>(&mut self, picture: &Picture<A>) {
+        use md5::Digest as _;
+
+        let hasher = &mut self.hasher;
+        for_each_row(picture, &mut self.buf, |row| {
+            hasher.update(row);
+            Ok(())
+        })
+        .expect("hashing can't fail");
+        self.frame_count += 1;
+    }
+
+    /// Number of pictures added so far.
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    /// Returns the digest over all pictures.
+    pub fn finish(self) -> Digest {
+        use md5::Digest as _;
+
+        Digest(self.hasher.finalize().into())
+    }
+}
+
+/// MD5 digest.
+///
+/// This is formatted as lowercase hexadecimal string, the same as the output file of
+/// `dav1d --muxer md5` without the trailing newline.
+#[cfg(feature = "md5")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Digest(pub [u8; 16]);
+
+#[cfg(feature = "md5")]
+impl fmt::Display for Digest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.iter().try_for_each(|b| write!(f, "{:02x}", b))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Decoder;
+
+    static TEST_FILE_420_8: &[u8] = include_bytes!("../test-420-8.ivf");
+    static TEST_FILE_420_12: &[u8] = include_bytes!("../test-420-12.ivf");
+
+    #[test]
+    fn test_write() {
+        for (file, bytes_per_sample) in [(TEST_FILE_420_8, 1), (TEST_FILE_420_12, 2)] {
+            let mut pictures = Vec::new();
+            crate::test::decode_file(file, Decoder::new().unwrap(), &mut pictures);
+            assert_eq!(pictures.len(), 5);
+            let mut w = Writer::new(Vec::new());
+            for p in &pictures {
+                w.write_picture(p).unwrap();
+            }
+            assert_eq!(w.frame_count(), 5);
+            let yuv = w.into_inner().unwrap();
+
+            let frame_len = (320 * 240 + 2 * 160 * 120) * bytes_per_sample;
+            assert_eq!(yuv.len(), 5 * frame_len);
+
+            // First row of each plane of the first picture
+            for (component, offset, len) in [
+                (PlanarImageComponent::Y, 0, 320),
+                (PlanarImageComponent::U, 320 * 240, 160),
+                (PlanarImageComponent::V, 320 * 240 + 160 * 120, 160),
+            ] {
+                let offset = offset * bytes_per_sample;
+                let len = len * bytes_per_sample;
+                assert_eq!(
+                    &yuv[offset..offset + len],
+                    &pictures[0].plane(component)[..len]
+                );
+            }
+        }
+    }
+
+    #[cfg(feature = "md5")]
+    #[test]
+    fn test_md5() {
+        use md5::Digest as _;
+
+        assert_eq!(
+            Digest(md5::Md5::digest(b"").into()).to_string(),
+            "d41d8cd98f00b204e9800998ecf8427e"
+        );
+
+        // Output of `dav1d --muxer md5` for the test files
+        for (file, digest) in [
+            (TEST_FILE_420_8, "fc70d4f3e24b5d3fdffcb3210405ce59"),
+            (TEST_FILE_420_12, "0232876e1d170b4ac1a71b7ad01226a0"),
+        ] {
+            let mut pictures = Vec::new();
+            crate::test::decode_file(file, Decoder::new().unwrap(), &mut pictures);
+            assert_eq!(pictures.len(), 5);
+            let mut w = Writer::new(Vec::new());
+            let mut md5 = Md5::new();
+            for p in &pictures {
+                w.write_picture(p).unwrap();
+                md5.write_picture(p);
+            }
+            assert_eq!(md5.frame_count(), 5);
+
+            let md5 = md5.finish();
+            assert_eq!(md5.to_string(), digest);
+
+            // Same data as the raw YUV output
+            let yuv = w.into_inner().unwrap();
+            assert_eq!(md5, Digest(md5::Md5::digest(&yuv).into()));
+        }
+    }
+}
@@ -1,83 +1,64 @@
-mod ivf {
-    use bitstream_io::{ByteRead, ByteReader, LittleEndian};
-    use std::io;
-
-    #[derive(Debug, PartialEq, Eq)]
-    pub struct Header {
-        pub tag: [u8; 4],
-        pub w: u16,
-        pub h: u16,
-        pub timebase_num: u32,
-        pub timebase_den: u32,
-    }
-
-    pub fn read_header(r: &mut dyn io::Read) -> io::Result<Header> {
-        let mut br = ByteReader::endian(r, LittleEndian);
-
-        let mut signature = [0u8; 4];
-        let mut tag = [0u8; 4];
-
-        br.read_bytes(&mut signature)?;
-        let v0 = br.read::<u16>()?;
-        let v1 = br.read::<u16>()?;
-        br.read_bytes(&mut tag)?;
-
-        log::debug!("sign {:?} version {} {} tag {:?}", &signature, v0, v1, &tag);
+use structopt::*;
 
-        let w = br.read::<u16>()?;
-        let h = br.read::<u16>()?;
+#[derive(StructOpt, Debug)]
+struct Opt {
+    #[structopt(name = "FILE", parse(from_os_str))]
+    input: std::path::PathBuf,
+    /// Write the decoded pictures to this file instead of printing them
+    ///
+    /// The format is selected by the extension: `.y4m` for YUV4MPEG2, `.md5` for an MD5 checksum
+    /// like `dav1d --muxer md5` and raw planar YUV otherwise.
+    #[structopt(short, long, parse(from_os_str))]
+    output: Option<std::path::PathBuf>,
+}
 
-        let timebase_den = br.read::<u32>()?;
-        let timebase_num = br.read::<u32>()?;
+use dav1d::{ivf, y4m, yuv};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Write};
 
-        let _ = br.read::<u32>()?;
-        let _ = br.read::<u32>()?;
+enum Output {
+    Print,
+    Y4m(y4m::Writer<BufWriter<File>>),
+    Yuv(yuv::Writer<BufWriter<File>>),
+    Md5(yuv::Md5, File),
+}
 
-        Ok(Header {
-            tag,
-            w,
-            h,
-            timebase_num,
-            timebase_den,
-        })
-    }
+impl Output {
+    fn write_picture(&mut self, p: &dav1d::Picture) -> io::Result<()> {
+        match self {
+            Output::Print => println!("{:?}", p),
+            Output::Y4m(w) => w.write_picture(p)?,
+            Output::Yuv(w) => w.write_picture(p)?,
+            Output::Md5(md5, _) => md5.write_picture(p),
+        }
 
-    pub struct Packet {
-        pub data: Box<[u8]>,
-        pub pts: u64,
+        Ok(())
     }
 
-    pub fn read_packet(r: &mut dyn io::Read) -> io::Result<Packet> {
-        let mut br = ByteReader::endian(r, LittleEndian);
-
-        let len = br.read::<u32>()?;
-        let pts = br.read::<u64>()?;
-        let mut buf = vec![0u8; len as usize];
-
-        br.read_bytes(&mut buf)?;
+    fn finish(self) -> io::Result<()> {
+        match self {
+            Output::Print => (),
+            Output::Y4m(w) => {
+                w.into_inner()?;
+            }
+            Output::Yuv(w) => {
+                w.into_inner()?;
+            }
+            Output::Md5(md5, mut f) => writeln!(f, "{}", md5.finish())?,
+        }
 
-        Ok(Packet {
-            data: buf.into_boxed_slice(),
-            pts,
-        })
+        Ok(())
     }
 }
 
-use structopt::*;
-
-#[derive(StructOpt, Debug)]
-struct Opt {
-    #[structopt(name = "FILE", parse(from_os_str))]
-    input: std::path::PathBuf,
-}
-
-use std::fs::File;
-use std::io::BufReader;
-
-fn handle_pending_pictures(dec: &mut dav1d::Decoder, drain: bool) {
+fn handle_pending_pictures(dec: &mut dav1d::Decoder, output: &mut Output, drain: bool) {
     loop {
         match dec.get_picture() {
-            Ok(p) => println!("{:?}", p),
+            Ok(p) => {
+                if let Err(e) = output.write_picture(&p) {
+                    panic!("Error writing picture: {}", e);
+                }
+            }
             // Need to send more data to the decoder before it can decode new pictures
             Err(e) if e.is_again() => return,
             Err(e) => {
@@ -95,13 +76,27 @@ fn main() -> std::io::Result<()> {
     let opt = Opt::from_args();
 
     let file = File::open(opt.input)?;
-    let mut r = BufReader::new(file);
-    let header = ivf::read_header(&mut r)?;
-    println!("{:?}", header);
+    let mut r = ivf::Reader::new(BufReader::new(file))?;
+    println!("{:?}", r.header());
+
+    let mut output = match opt.output {
+        Some(path) => {
+            let extension = path.extension().and_then(|e| e.to_str()).map(str::to_owned);
+            let f = File::create(path)?;
+            match extension.as_deref() {
+                Some("y4m") => {
+                    Output::Y4m(y4m::Writer::from_ivf_header(BufWriter::new(f), r.header()))
+                }
+                Some("md5") => Output::Md5(yuv::Md5::new(), f),
+                _ => Output::Yuv(yuv::Writer::new(BufWriter::new(f))),
+            }
+        }
+        None => Output::Print,
+    };
 
     let mut dec = dav1d::Decoder::new().expect("failed to create decoder instance");
 
-    while let Ok(packet) = ivf::read_packet(&mut r) {
+    while let Some(packet) = r.read_packet()? {
         println!("Packet {}", packet.pts);
 
         // Send packet to the decoder
@@ -111,7 +106,7 @@ fn main() -> std::io::Result<()> {
                 // pending pictures and send pending data to the decoder
                 // until it is all used up.
                 loop {
-                    handle_pending_pictures(&mut dec, false);
+                    handle_pending_pictures(&mut dec, &mut output, false);
 
                     match dec.send_pending_data() {
                         Err(e) if e.is_again() => continue,
@@ -129,11 +124,11 @@ fn main() -> std::io::Result<()> {
         }
 
         // Handle all pending pictures before sending the next data.
-        handle_pending_pictures(&mut dec, false);
+        handle_pending_pictures(&mut dec, &mut output, false);
     }
 
     // Handle all pending pictures that were not output yet.
-    handle_pending_pictures(&mut dec, true);
+    handle_pending_pictures(&mut dec, &mut output, true);
 
-    Ok(())
+    output.finish()
 }